@@ -0,0 +1,500 @@
+use std::{
+    collections::{HashMap, HashSet},
+    hash::Hash,
+};
+
+/// Which cells neighbour each other.
+pub trait Topology {
+    type Cell: Copy + Eq + Hash;
+
+    fn for_each_neighbor<F: FnMut(Self::Cell)>(&self, cell: Self::Cell, f: F);
+}
+
+/// A topology with finitely many cells, numbered from zero.
+pub trait Finite: Topology<Cell = usize> {
+    fn len(&self) -> usize;
+
+    /// Whether the cell takes part in the automaton at all.
+    fn contains(&self, _cell: usize) -> bool {
+        true
+    }
+}
+
+/// Decides whether a cell is alive in the next generation from the number of
+/// live neighbours it has.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rule {
+    birth: Vec<bool>,
+    survival: Vec<bool>,
+}
+
+impl Rule {
+    /// A dead cell comes alive if its live neighbour count is in `birth`, and
+    /// a live cell stays alive if its count is in `survival`.
+    pub fn new<B, S>(birth: B, survival: S) -> Self
+    where
+        B: IntoIterator<Item = usize>,
+        S: IntoIterator<Item = usize>,
+    {
+        fn to_table<I: IntoIterator<Item = usize>>(counts: I) -> Vec<bool> {
+            let mut table = Vec::new();
+            for count in counts {
+                if count >= table.len() {
+                    table.resize(count + 1, false);
+                }
+                table[count] = true;
+            }
+            table
+        }
+
+        Self {
+            birth: to_table(birth),
+            survival: to_table(survival),
+        }
+    }
+
+    pub fn is_alive_next(&self, is_alive: bool, neighbors: usize) -> bool {
+        let table = if is_alive {
+            &self.survival
+        } else {
+            &self.birth
+        };
+        table.get(neighbors).copied().unwrap_or(false)
+    }
+}
+
+pub trait Automaton {
+    /// Advances one generation, returning whether anything changed.
+    fn step(&mut self) -> bool;
+
+    fn population(&self) -> usize;
+
+    fn run(&mut self, generations: usize) {
+        for _ in 0..generations {
+            self.step();
+        }
+    }
+
+    /// Steps until a generation doesn't change anything, returning how many
+    /// generations changed. This never returns for patterns that don't
+    /// settle down.
+    fn run_until_stable(&mut self) -> usize {
+        let mut generations = 0;
+        while self.step() {
+            generations += 1;
+        }
+        generations
+    }
+}
+
+/// An automaton on an unbounded topology, tracking only the live cells.
+///
+/// Only cells next to a live cell are considered for birth, so the rule must
+/// not bring cells with no live neighbours to life. Every live cell is
+/// checked for survival, including ones with no live neighbours.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sparse<T: Topology> {
+    topology: T,
+    rule: Rule,
+    live: HashSet<T::Cell>,
+}
+
+impl<T: Topology> Sparse<T> {
+    pub fn new<I>(topology: T, rule: Rule, live: I) -> Self
+    where
+        I: IntoIterator<Item = T::Cell>,
+    {
+        assert!(
+            !rule.is_alive_next(false, 0),
+            "Sparse automata can't give birth to isolated cells."
+        );
+        Self {
+            topology,
+            rule,
+            live: live.into_iter().collect(),
+        }
+    }
+}
+
+impl<T: Topology> Automaton for Sparse<T> {
+    fn step(&mut self) -> bool {
+        // Live cells start at zero so that isolated ones are still checked
+        // for survival.
+        let mut counts = self
+            .live
+            .iter()
+            .map(|cell| (*cell, 0))
+            .collect::<HashMap<_, _>>();
+        for cell in &self.live {
+            self.topology
+                .for_each_neighbor(*cell, |n| *counts.entry(n).or_insert(0) += 1);
+        }
+
+        let live = &self.live;
+        let rule = &self.rule;
+        let next = counts
+            .into_iter()
+            .filter(|(cell, count)| rule.is_alive_next(live.contains(cell), *count))
+            .map(|(cell, _)| cell)
+            .collect::<HashSet<_>>();
+
+        let changed = next != self.live;
+        self.live = next;
+        changed
+    }
+
+    fn population(&self) -> usize {
+        self.live.len()
+    }
+}
+
+/// An automaton on a finite topology, storing every cell's state.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dense<T: Finite> {
+    topology: T,
+    rule: Rule,
+    live: Vec<bool>,
+    next: Vec<bool>,
+}
+
+impl<T: Finite> Dense<T> {
+    pub fn new<I>(topology: T, rule: Rule, live: I) -> Self
+    where
+        I: IntoIterator<Item = usize>,
+    {
+        let mut cells = vec![false; topology.len()];
+        for cell in live {
+            cells[cell] = true;
+        }
+        Self {
+            next: cells.clone(),
+            live: cells,
+            topology,
+            rule,
+        }
+    }
+
+    pub fn is_alive(&self, cell: usize) -> bool {
+        self.live[cell]
+    }
+}
+
+impl<T: Finite> Automaton for Dense<T> {
+    fn step(&mut self) -> bool {
+        let mut changed = false;
+        for cell in 0..self.live.len() {
+            if !self.topology.contains(cell) {
+                continue;
+            }
+            let mut count = 0;
+            let live = &self.live;
+            self.topology.for_each_neighbor(cell, |n| {
+                if live[n] {
+                    count += 1;
+                }
+            });
+            let is_alive = self.rule.is_alive_next(live[cell], count);
+            changed |= is_alive != live[cell];
+            self.next[cell] = is_alive;
+        }
+        std::mem::swap(&mut self.live, &mut self.next);
+        changed
+    }
+
+    fn population(&self) -> usize {
+        self.live.iter().filter(|c| **c).count()
+    }
+}
+
+/// The integer lattice in `N` dimensions, where every cell touching another,
+/// even diagonally, is its neighbour.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Moore<const N: usize>;
+
+impl<const N: usize> Topology for Moore<N> {
+    type Cell = [i64; N];
+
+    fn for_each_neighbor<F: FnMut(Self::Cell)>(&self, cell: Self::Cell, mut f: F) {
+        let mut offset = [-1; N];
+        loop {
+            if offset.iter().any(|o| *o != 0) {
+                let mut neighbor = cell;
+                for (n, o) in neighbor.iter_mut().zip(&offset) {
+                    *n += o;
+                }
+                f(neighbor);
+            }
+
+            // Count through the offsets in base 3.
+            let mut i = 0;
+            while i < N && offset[i] == 1 {
+                offset[i] = -1;
+                i += 1;
+            }
+            if i == N {
+                return;
+            }
+            offset[i] += 1;
+        }
+    }
+}
+
+/// A hexagonal tiling in axial coordinates, where the neighbours of `(x, y)`
+/// are `(x ± 1, y)`, `(x, y ± 1)`, `(x + 1, y + 1)` and `(x - 1, y - 1)`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Hex;
+
+impl Hex {
+    pub const OFFSETS: [(i64, i64); 6] = [(1, 0), (0, -1), (-1, -1), (-1, 0), (0, 1), (1, 1)];
+}
+
+impl Topology for Hex {
+    type Cell = (i64, i64);
+
+    fn for_each_neighbor<F: FnMut(Self::Cell)>(&self, (x, y): Self::Cell, mut f: F) {
+        for (dx, dy) in &Self::OFFSETS {
+            f((x + dx, y + dy));
+        }
+    }
+}
+
+/// Which cells of a grid count as neighbours.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reach {
+    /// The nearest cell in each of the eight directions, if it's adjacent.
+    Adjacent,
+    /// The nearest cell in each of the eight directions, however far away,
+    /// skipping over any gaps.
+    LineOfSight,
+}
+
+/// A rectangular grid with gaps, numbered row by row.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Grid {
+    width: usize,
+    height: usize,
+    present: Vec<bool>,
+    reach: Reach,
+}
+
+impl Grid {
+    pub fn new(width: usize, present: Vec<bool>, reach: Reach) -> Self {
+        Self {
+            width,
+            height: present.len() / width.max(1),
+            present,
+            reach,
+        }
+    }
+
+    /// Walks from `cell` in direction `(dr, dc)` to the first cell that's
+    /// present, within the grid's reach.
+    fn look(&self, cell: usize, (dr, dc): (isize, isize)) -> Option<usize> {
+        let (mut row, mut col) = ((cell / self.width) as isize, (cell % self.width) as isize);
+        loop {
+            row += dr;
+            col += dc;
+            if row < 0 || col < 0 || row >= self.height as isize || col >= self.width as isize {
+                return None;
+            }
+            let index = row as usize * self.width + col as usize;
+            if self.present[index] {
+                return Some(index);
+            }
+            if self.reach == Reach::Adjacent {
+                return None;
+            }
+        }
+    }
+}
+
+impl Topology for Grid {
+    type Cell = usize;
+
+    fn for_each_neighbor<F: FnMut(usize)>(&self, cell: usize, mut f: F) {
+        const DIRECTIONS: [(isize, isize); 8] = [
+            (-1, -1),
+            (-1, 0),
+            (-1, 1),
+            (0, -1),
+            (0, 1),
+            (1, -1),
+            (1, 0),
+            (1, 1),
+        ];
+        for direction in &DIRECTIONS {
+            if let Some(neighbor) = self.look(cell, *direction) {
+                f(neighbor);
+            }
+        }
+    }
+}
+
+impl Finite for Grid {
+    fn len(&self) -> usize {
+        self.present.len()
+    }
+
+    fn contains(&self, cell: usize) -> bool {
+        self.present[cell]
+    }
+}
+
+/// A finite topology with every cell's neighbours worked out once up front,
+/// stored as a compressed sparse row table.
+///
+/// Stepping over this is a flat pass through one array, however expensive
+/// finding the neighbours was in the original topology.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Precomputed {
+    present: Vec<bool>,
+    /// Cell `i`'s neighbours are `neighbors[starts[i]..starts[i + 1]]`.
+    starts: Vec<usize>,
+    neighbors: Vec<usize>,
+}
+
+impl Precomputed {
+    pub fn new<T: Finite>(topology: &T) -> Self {
+        let len = topology.len();
+        let present = (0..len).map(|c| topology.contains(c)).collect::<Vec<_>>();
+        let mut starts = Vec::with_capacity(len + 1);
+        let mut neighbors = Vec::new();
+        for (cell, is_present) in present.iter().enumerate() {
+            starts.push(neighbors.len());
+            if *is_present {
+                topology.for_each_neighbor(cell, |n| neighbors.push(n));
+            }
+        }
+        starts.push(neighbors.len());
+        Self {
+            present,
+            starts,
+            neighbors,
+        }
+    }
+
+    pub fn neighbors(&self, cell: usize) -> &[usize] {
+        &self.neighbors[self.starts[cell]..self.starts[cell + 1]]
+    }
+}
+
+impl Topology for Precomputed {
+    type Cell = usize;
+
+    fn for_each_neighbor<F: FnMut(usize)>(&self, cell: usize, f: F) {
+        self.neighbors(cell).iter().copied().for_each(f);
+    }
+}
+
+impl Finite for Precomputed {
+    fn len(&self) -> usize {
+        self.present.len()
+    }
+
+    fn contains(&self, cell: usize) -> bool {
+        self.present[cell]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Automaton, Dense, Grid, Hex, Moore, Precomputed, Reach, Rule, Sparse, Topology};
+
+    #[test]
+    fn moore_has_all_neighbors() {
+        let mut neighbors = Vec::new();
+        Moore::<2>.for_each_neighbor([5, 5], |n| neighbors.push(n));
+        neighbors.sort();
+        assert_eq!(
+            neighbors,
+            vec![
+                [4, 4],
+                [4, 5],
+                [4, 6],
+                [5, 4],
+                [5, 6],
+                [6, 4],
+                [6, 5],
+                [6, 6]
+            ]
+        );
+
+        let mut count = 0;
+        Moore::<4>.for_each_neighbor([0; 4], |_| count += 1);
+        assert_eq!(count, 80);
+    }
+
+    #[test]
+    fn sparse_blinker_oscillates() {
+        let rule = Rule::new(vec![3], vec![2, 3]);
+        let mut life = Sparse::new(Moore::<2>, rule, vec![[0, -1], [0, 0], [0, 1]]);
+        assert!(life.step());
+        assert_eq!(life.population(), 3);
+        life.run(2);
+        assert!(life.live.contains(&[-1, 0]));
+        assert!(life.live.contains(&[1, 0]));
+    }
+
+    #[test]
+    fn sparse_block_is_stable() {
+        let rule = Rule::new(vec![3], vec![2, 3]);
+        let block = vec![[0, 0], [0, 1], [1, 0], [1, 1]];
+        let mut life = Sparse::new(Moore::<2>, rule, block);
+        assert_eq!(life.run_until_stable(), 0);
+        assert_eq!(life.population(), 4);
+    }
+
+    #[test]
+    fn sparse_keeps_isolated_survivors() {
+        let rule = Rule::new(vec![3], vec![0]);
+        let mut life = Sparse::new(Moore::<2>, rule, vec![[0, 0], [5, 5]]);
+        assert_eq!(life.run_until_stable(), 0);
+        assert_eq!(life.population(), 2);
+    }
+
+    #[test]
+    fn hex_has_six_neighbors() {
+        let mut neighbors = Vec::new();
+        Hex.for_each_neighbor((0, 0), |n| neighbors.push(n));
+        assert_eq!(neighbors.len(), 6);
+        assert!(!neighbors.contains(&(1, -1)));
+    }
+
+    #[test]
+    fn grid_skips_gaps_in_line_of_sight() {
+        // #.#
+        // ...
+        // #.#
+        let present = vec![true, false, true, false, false, false, true, false, true];
+        let mut adjacent = Vec::new();
+        Grid::new(3, present.clone(), Reach::Adjacent).for_each_neighbor(0, |n| adjacent.push(n));
+        assert_eq!(adjacent, Vec::<usize>::new());
+
+        let mut visible = Vec::new();
+        Grid::new(3, present, Reach::LineOfSight).for_each_neighbor(0, |n| visible.push(n));
+        assert_eq!(visible, vec![2, 6, 8]);
+    }
+
+    #[test]
+    fn dense_ignores_missing_cells() {
+        let present = vec![true, false, true];
+        let rule = Rule::new(vec![0], vec![]);
+        let mut automaton = Dense::new(Grid::new(3, present, Reach::Adjacent), rule, vec![]);
+        assert!(automaton.step());
+        assert_eq!(automaton.live, vec![true, false, true]);
+    }
+
+    #[test]
+    fn precomputed_matches_original() {
+        // ##.
+        // .#.
+        // #.#
+        let present = vec![true, true, false, false, true, false, true, false, true];
+        let grid = Grid::new(3, present, Reach::LineOfSight);
+        let table = Precomputed::new(&grid);
+        assert_eq!(table.neighbors(0), &[1, 6, 4]);
+        assert_eq!(table.neighbors(2), &[] as &[usize]);
+        assert_eq!(table.neighbors(4), &[0, 1, 6, 8]);
+        assert_eq!(table.neighbors(6), &[0, 4, 8]);
+    }
+}
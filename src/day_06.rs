@@ -1,33 +1,238 @@
+use self::answer_set::{AnswerSet, Questions};
+
+mod answer_set;
+
 pub fn star_1(data: String) {
-    let groups = parse_groups(&data);
-    let total: u32 = groups
-        .into_iter()
-        .map(|v| v.into_iter().fold(0, |acc, x| acc | x))
-        .map(|c| c.count_ones())
+    let (_, groups) = parse_groups(&data);
+    let total: usize = groups
+        .iter()
+        .map(|v| AnswerSet::at_least(v, 1))
+        .map(|s| s.len())
         .sum();
     println!("{:?}", total);
 }
 
 pub fn star_2(data: String) {
-    let groups = parse_groups(&data);
-    let total: u32 = groups
-        .into_iter()
-        .map(|v| v.into_iter().fold(0xffffffff, |acc, x| acc & x))
-        .map(|c| c.count_ones())
+    let (_, groups) = parse_groups(&data);
+    let total: usize = groups
+        .iter()
+        .map(|v| AnswerSet::at_least(v, v.len()))
+        .map(|s| s.len())
         .sum();
     println!("{:?}", total);
 }
 
-fn parse_groups(data: &str) -> Vec<Vec<u32>> {
-    Groups::new(data.lines().map(str::trim))
-        .map(|v| v.into_iter().map(parse_answers).collect())
+pub fn shared(data: String, _args: &[String]) {
+    let (questions, groups) = parse_groups(&data);
+    for (i, group) in groups.iter().enumerate() {
+        let any = group.iter().fold(AnswerSet::new(), |acc, x| acc.union(x));
+        let all = group
+            .iter()
+            .skip(1)
+            .fold(group[0].clone(), |acc, x| acc.intersection(x));
+        let some = any.symmetric_difference(&all);
+        let majority = AnswerSet::at_least(group, group.len() / 2 + 1);
+        println!(
+            "group {} ({} members): all [{}], majority [{}], some [{}], any [{}]{}",
+            i + 1,
+            group.len(),
+            questions.labels(&all),
+            questions.labels(&majority),
+            questions.labels(&some),
+            questions.labels(&any),
+            if some.is_empty() { " (unanimous)" } else { "" },
+        );
+    }
+}
+
+pub fn stats(data: String, args: &[String]) {
+    let csv = args.iter().any(|a| a == "--csv");
+    let (questions, groups) = parse_groups(&data);
+    if csv {
+        print!("{}", stats_csv(&questions, &groups));
+        return;
+    }
+
+    let stats = groups
+        .iter()
+        .map(|g| GroupStats::new(g))
+        .collect::<Vec<_>>();
+    println!(
+        "{:>6} {:>5} {:>4} {:>4}  distribution",
+        "group", "size", "any", "all"
+    );
+    for (i, group) in stats.iter().enumerate() {
+        let distribution = group
+            .distribution()
+            .iter()
+            .enumerate()
+            .skip(1)
+            .map(|(k, n)| format!("{}:{}", k, n))
+            .collect::<Vec<_>>();
+        println!(
+            "{:>6} {:>5} {:>4} {:>4}  {}",
+            i + 1,
+            group.size,
+            group.answered_by_any(),
+            group.answered_by_all(),
+            distribution.join(" "),
+        );
+    }
+
+    let summary = QuestionSummary::new(&questions, &groups);
+    println!();
+    let max = summary.counts.iter().copied().max().unwrap_or(0).max(1);
+    for (label, count) in summary.by_question() {
+        // Rounds up, so every question anyone answered gets a mark.
+        let bar = "#".repeat((count as f64 * 50.0 / max as f64).ceil() as usize);
+        println!("{} {:>5} {}", label, count, bar);
+    }
+
+    if let Some(((most, most_count), (least, least_count))) = summary.extremes() {
+        println!();
+        println!("most common: [{}] ({} members)", most, most_count);
+        println!("least common: [{}] ({} members)", least, least_count);
+    }
+}
+
+/// The whole report as one CSV table, with a row for each value. The first
+/// column says which part of the report the row is from, and the next two
+/// say which group or question it's about and what the value is.
+fn stats_csv(questions: &Questions, groups: &[Vec<AnswerSet>]) -> String {
+    let mut rows = vec![["section", "name", "field", "value"].map(String::from)];
+    let mut row = |section: &str, name: String, field: String, value: String| {
+        rows.push([section.to_string(), name, field, value]);
+    };
+
+    for (i, group) in groups.iter().enumerate() {
+        let stats = GroupStats::new(group);
+        let name = (i + 1).to_string();
+        row("group", name.clone(), "size".into(), stats.size.to_string());
+        let any = stats.answered_by_any().to_string();
+        row("group", name.clone(), "any".into(), any);
+        let all = stats.answered_by_all().to_string();
+        row("group", name.clone(), "all".into(), all);
+        for (k, n) in stats.distribution().iter().enumerate().skip(1) {
+            let field = format!("answered by {}", k);
+            row("group", name.clone(), field, n.to_string());
+        }
+    }
+
+    let summary = QuestionSummary::new(questions, groups);
+    for (label, count) in summary.by_question() {
+        row(
+            "question",
+            label.to_string(),
+            "members".into(),
+            count.to_string(),
+        );
+    }
+    if let Some((most, least)) = summary.extremes() {
+        for (name, (labels, count)) in [("most common", most), ("least common", least)] {
+            row("summary", name.into(), "questions".into(), labels);
+            row("summary", name.into(), "members".into(), count.to_string());
+        }
+    }
+
+    rows.iter()
+        .map(|row| {
+            let fields = row.iter().map(|f| csv_field(f)).collect::<Vec<_>>();
+            format!("{}\n", fields.join(","))
+        })
         .collect()
 }
 
-fn parse_answers(s: &str) -> u32 {
-    s.chars()
-        .map(|c| c as u32 - 'a' as u32)
-        .fold(0, |acc, x| acc | (1 << x))
+/// Quotes a field if it has anything in it that CSV treats specially.
+fn csv_field(field: &str) -> String {
+    if field.contains(&[',', '"', '\n', '\r'][..]) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// How many members answered each question, across every group.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct QuestionSummary {
+    labels: Vec<char>,
+    counts: Vec<usize>,
+}
+
+impl QuestionSummary {
+    fn new(questions: &Questions, groups: &[Vec<AnswerSet>]) -> Self {
+        let counts = AnswerSet::counts(groups.iter().flatten());
+        let labels = (0..questions.len()).map(|i| questions.label(i)).collect();
+        Self { labels, counts }
+    }
+
+    /// Each question's label and count, in order of label.
+    fn by_question(&self) -> Vec<(char, usize)> {
+        let mut questions = self
+            .labels
+            .iter()
+            .copied()
+            .zip(self.counts.iter().copied())
+            .collect::<Vec<_>>();
+        questions.sort_unstable();
+        questions
+    }
+
+    /// The labels of the most and least commonly answered questions, along
+    /// with their counts, or `None` if there are no questions.
+    fn extremes(&self) -> Option<((String, usize), (String, usize))> {
+        let most = self.counts.iter().copied().max()?;
+        let least = self.counts.iter().copied().min()?;
+        let with_count = |n: usize| {
+            let labels = self
+                .by_question()
+                .into_iter()
+                .filter(|(_, count)| *count == n)
+                .map(|(label, _)| label)
+                .collect::<String>();
+            (labels, n)
+        };
+        Some((with_count(most), with_count(least)))
+    }
+}
+
+fn parse_groups(data: &str) -> (Questions, Vec<Vec<AnswerSet>>) {
+    let mut questions = Questions::new();
+    let groups = Groups::new(data.lines().map(str::trim))
+        .map(|v| v.into_iter().map(|s| questions.parse(s)).collect())
+        .collect();
+    (questions, groups)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct GroupStats {
+    size: usize,
+    counts: Vec<usize>,
+}
+
+impl GroupStats {
+    fn new(group: &[AnswerSet]) -> Self {
+        Self {
+            size: group.len(),
+            counts: AnswerSet::counts(group),
+        }
+    }
+
+    fn answered_by_any(&self) -> usize {
+        self.counts.iter().filter(|c| **c > 0).count()
+    }
+
+    fn answered_by_all(&self) -> usize {
+        self.counts.iter().filter(|c| **c == self.size).count()
+    }
+
+    /// The number of questions answered by exactly `k` members, indexed by `k`.
+    fn distribution(&self) -> Vec<usize> {
+        let mut distribution = vec![0; self.size + 1];
+        for count in &self.counts {
+            distribution[*count] += 1;
+        }
+        distribution
+    }
 }
 
 struct Groups<I> {
@@ -64,10 +269,96 @@ where
 
 #[cfg(test)]
 mod tests {
-    use super::parse_answers;
+    use super::{
+        answer_set::{AnswerSet, Questions},
+        csv_field, parse_groups, stats_csv, GroupStats,
+    };
 
     #[test]
     fn answers_parse() {
-        assert_eq!(parse_answers("heqznia"), 0b10000000010010000110010001);
+        let mut questions = Questions::new();
+        let set = questions.parse("heqznia");
+        assert_eq!(set.len(), 7);
+        assert_eq!(questions.labels(&set), "aehinqz");
+    }
+
+    #[test]
+    fn answers_parse_unicode() {
+        let mut questions = Questions::new();
+        let set = questions.parse("äßλ😀");
+        assert_eq!(set.len(), 4);
+        assert_eq!(questions.labels(&set), "ßäλ😀");
+    }
+
+    #[test]
+    fn answer_sets_combine() {
+        let mut questions = Questions::new();
+        let a = questions.parse("abc");
+        let b = questions.parse("bcd");
+        assert_eq!(questions.labels(&a.union(&b)), "abcd");
+        assert_eq!(questions.labels(&a.intersection(&b)), "bc");
+        assert_eq!(questions.labels(&a.symmetric_difference(&b)), "ad");
+        assert_eq!(a.symmetric_difference(&a), AnswerSet::new());
+    }
+
+    #[test]
+    fn at_least_counts_members() {
+        let mut questions = Questions::new();
+        let group = vec![
+            questions.parse("ab"),
+            questions.parse("bc"),
+            questions.parse("b"),
+        ];
+        assert_eq!(questions.labels(&AnswerSet::at_least(&group, 1)), "abc");
+        assert_eq!(questions.labels(&AnswerSet::at_least(&group, 2)), "b");
+        assert_eq!(questions.labels(&AnswerSet::at_least(&group, 3)), "b");
+    }
+
+    #[test]
+    fn answer_set_spans_multiple_words() {
+        let mut set = AnswerSet::new();
+        set.insert(3);
+        set.insert(200);
+        assert_eq!(set.len(), 2);
+        assert_eq!(set.iter().collect::<Vec<_>>(), vec![3, 200]);
+    }
+
+    #[test]
+    fn group_stats_summarize_group() {
+        let (_, groups) = parse_groups("ab\nac\nabd\n");
+        let stats = GroupStats::new(&groups[0]);
+        assert_eq!(stats.size, 3);
+        assert_eq!(stats.answered_by_any(), 4);
+        assert_eq!(stats.answered_by_all(), 1);
+        assert_eq!(stats.distribution(), vec![0, 2, 1, 1]);
+    }
+
+    #[test]
+    fn stats_csv_is_one_table() {
+        let (questions, groups) = parse_groups("ab\nb\n\n,\n\"b\n");
+        assert_eq!(
+            stats_csv(&questions, &groups),
+            "section,name,field,value\n\
+             group,1,size,2\n\
+             group,1,any,2\n\
+             group,1,all,1\n\
+             group,1,answered by 1,1\n\
+             group,1,answered by 2,1\n\
+             group,2,size,2\n\
+             group,2,any,3\n\
+             group,2,all,0\n\
+             group,2,answered by 1,3\n\
+             group,2,answered by 2,0\n\
+             question,\"\"\"\",members,1\n\
+             question,\",\",members,1\n\
+             question,a,members,1\n\
+             question,b,members,3\n\
+             summary,most common,questions,b\n\
+             summary,most common,members,3\n\
+             summary,least common,questions,\"\"\",a\"\n\
+             summary,least common,members,1\n"
+        );
+        assert_eq!(csv_field("plain"), "plain");
+        assert_eq!(csv_field("a\"b"), "\"a\"\"b\"");
     }
 }
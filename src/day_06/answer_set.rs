@@ -0,0 +1,144 @@
+use std::collections::HashMap;
+
+const WORD_BITS: usize = 64;
+
+/// Assigns each question label a bit index, in order of first appearance.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Questions {
+    labels: Vec<char>,
+    indices: HashMap<char, usize>,
+}
+
+impl Questions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn index_of(&mut self, label: char) -> usize {
+        let labels = &mut self.labels;
+        *self.indices.entry(label).or_insert_with(|| {
+            labels.push(label);
+            labels.len() - 1
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.labels.len()
+    }
+
+    pub fn label(&self, index: usize) -> char {
+        self.labels[index]
+    }
+
+    pub fn parse(&mut self, answers: &str) -> AnswerSet {
+        let mut set = AnswerSet::new();
+        for c in answers.chars() {
+            set.insert(self.index_of(c));
+        }
+        set
+    }
+
+    /// The labels of every question in `set`, sorted.
+    pub fn labels(&self, set: &AnswerSet) -> String {
+        let mut labels = set.iter().map(|i| self.label(i)).collect::<Vec<_>>();
+        labels.sort_unstable();
+        labels.into_iter().collect()
+    }
+}
+
+/// A set of answered questions, stored as a bitset over question indices.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct AnswerSet {
+    words: Vec<u64>,
+}
+
+impl AnswerSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, index: usize) {
+        let word = index / WORD_BITS;
+        if word >= self.words.len() {
+            self.words.resize(word + 1, 0);
+        }
+        self.words[word] |= 1 << (index % WORD_BITS);
+    }
+
+    pub fn len(&self) -> usize {
+        self.words.iter().map(|w| w.count_ones() as usize).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.words.iter().all(|w| *w == 0)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        self.words.iter().enumerate().flat_map(|(i, w)| {
+            (0..WORD_BITS)
+                .filter(move |b| w & (1 << b) != 0)
+                .map(move |b| i * WORD_BITS + b)
+        })
+    }
+
+    pub fn union(&self, other: &Self) -> Self {
+        self.zip_with(other, |a, b| a | b)
+    }
+
+    pub fn intersection(&self, other: &Self) -> Self {
+        self.zip_with(other, |a, b| a & b)
+    }
+
+    pub fn symmetric_difference(&self, other: &Self) -> Self {
+        self.zip_with(other, |a, b| a ^ b)
+    }
+
+    /// The questions answered in at least `k` of `sets`.
+    pub fn at_least<'a, I>(sets: I, k: usize) -> Self
+    where
+        I: IntoIterator<Item = &'a Self>,
+    {
+        let mut set = Self::new();
+        for (index, _) in Self::counts(sets)
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| **c >= k)
+        {
+            set.insert(index);
+        }
+        set
+    }
+
+    /// How many of `sets` answered each question, indexed by question.
+    pub fn counts<'a, I>(sets: I) -> Vec<usize>
+    where
+        I: IntoIterator<Item = &'a Self>,
+    {
+        let mut counts = Vec::new();
+        for index in sets.into_iter().flat_map(Self::iter) {
+            if index >= counts.len() {
+                counts.resize(index + 1, 0);
+            }
+            counts[index] += 1;
+        }
+        counts
+    }
+
+    fn zip_with<F>(&self, other: &Self, f: F) -> Self
+    where
+        F: Fn(u64, u64) -> u64,
+    {
+        let len = self.words.len().max(other.words.len());
+        let mut words = (0..len)
+            .map(|i| {
+                let a = self.words.get(i).copied().unwrap_or(0);
+                let b = other.words.get(i).copied().unwrap_or(0);
+                f(a, b)
+            })
+            .collect::<Vec<_>>();
+        while words.last() == Some(&0) {
+            words.pop();
+        }
+        Self { words }
+    }
+}
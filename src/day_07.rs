@@ -8,78 +8,152 @@ use nom::{
     sequence::preceded,
     IResult,
 };
-use std::collections::{HashMap, HashSet};
+use std::{fmt, fs};
+
+use self::{
+    dot::{to_dot, Subgraph},
+    graph::BagGraph,
+};
+
+mod dot;
+mod graph;
+
+const SHINY_GOLD: Bag = Bag {
+    adjective: "shiny",
+    color: "gold",
+};
 
 pub fn star_1(data: String) {
-    let rules = parse(&data);
-    let mut inv_graph = HashMap::new();
-    for rule in rules {
-        for contained in rule.contains {
-            inv_graph
-                .entry(contained.bag)
-                .or_insert_with(HashSet::new)
-                .insert(rule.container);
-        }
+    let graph = match build_graph(&data) {
+        Some(graph) => graph,
+        None => return,
+    };
+    match graph.containers(SHINY_GOLD) {
+        Some(containers) => println!("{}", containers.len()),
+        None => println!("{} is not mentioned by any rule.", SHINY_GOLD),
     }
-    let mut set = HashSet::new();
-    for bag in &inv_graph[&Bag {
-        adjective: "shiny",
-        color: "gold",
-    }] {
-        set.insert(*bag);
+}
+
+pub fn star_2(data: String) {
+    let graph = match build_graph(&data) {
+        Some(graph) => graph,
+        None => return,
+    };
+    match graph.total_contents(SHINY_GOLD) {
+        Some(count) => println!("{}", count),
+        None => println!("{} is not mentioned by any rule.", SHINY_GOLD),
     }
+}
 
-    loop {
-        let mut new_set = set.clone();
-        for bag in &set {
-            if let Some(bags) = inv_graph.get(bag) {
-                new_set = new_set.union(bags).copied().collect::<HashSet<Bag>>();
-            }
+pub fn query(data: String, args: &[String]) {
+    let graph = match build_graph(&data) {
+        Some(graph) => graph,
+        None => return,
+    };
+    let bag = match args {
+        [adjective, color, ..] => Bag { adjective, color },
+        _ => {
+            println!("Expected a bag, like shiny gold.");
+            return;
         }
-        if new_set == set {
-            break;
+    };
+
+    let mut containers = match graph.containers(bag) {
+        Some(containers) => containers,
+        None => {
+            println!("{} is not mentioned by any rule.", bag);
+            return;
         }
-        set = new_set;
+    };
+    containers.sort_by_key(|b| (b.adjective, b.color));
+    println!("{} is inside {} bags:", bag, containers.len());
+    for container in containers {
+        println!("  {}", container);
+    }
+
+    let mut contents = graph.contents(bag).unwrap().into_iter().collect::<Vec<_>>();
+    contents.sort_by_key(|(b, _)| (b.adjective, b.color));
+    println!(
+        "{} holds {} bags:",
+        bag,
+        contents.iter().map(|(_, n)| n).sum::<u64>()
+    );
+    for (contained, amount) in contents {
+        println!("  {} {}", amount, contained);
     }
 
-    println!("{}", set.len());
+    if let [_, _, adjective, color, ..] = args {
+        let inner = Bag { adjective, color };
+        let show = |chain: Option<Vec<Bag>>| match chain {
+            Some(chain) => chain
+                .iter()
+                .map(Bag::to_string)
+                .collect::<Vec<_>>()
+                .join(" > "),
+            None => "none".to_string(),
+        };
+        println!("shortest chain: {}", show(graph.shortest_chain(bag, inner)));
+        println!("longest chain: {}", show(graph.longest_chain(bag, inner)));
+    }
 }
 
-pub fn star_2(data: String) {
-    let rules = parse(&data);
-    let graph = rules
-        .map(|rule| (rule.container, rule.contains))
-        .collect::<HashMap<_, _>>();
-    let mut counts = HashMap::new();
-
-    fn calculate_count<'a>(
-        bag: Bag<'a>,
-        graph: &HashMap<Bag<'a>, Vec<Contained<'a>>>,
-        counts: &mut HashMap<Bag<'a>, u32>,
-    ) -> u32 {
-        if let Some(count) = counts.get(&bag) {
-            return *count;
-        }
+pub fn validate(data: String, args: &[String]) {
+    let graph = match build_graph(&data) {
+        Some(graph) => graph,
+        None => return,
+    };
+    println!("Rules are valid.");
 
-        let contained = &graph[&bag];
-        let count = contained
-            .iter()
-            .map(|c| c.amount * (1 + calculate_count(c.bag, graph, counts)))
-            .sum();
-        counts.insert(bag, count);
-        count
+    let root = match args {
+        [adjective, color, ..] => Bag { adjective, color },
+        _ => SHINY_GOLD,
+    };
+    match graph.unrelated_to(root) {
+        Some(mut unrelated) => {
+            unrelated.sort_by_key(|b| (b.adjective, b.color));
+            println!("{} bags are unreachable from {}:", unrelated.len(), root);
+            for bag in unrelated {
+                println!("  {}", bag);
+            }
+        }
+        None => println!("{} is not mentioned by any rule.", root),
     }
+}
 
-    let count = calculate_count(
-        Bag {
-            adjective: "shiny",
-            color: "gold",
-        },
-        &graph,
-        &mut counts,
-    );
+pub fn dot(data: String, args: &[String]) {
+    let rules = parse(&data).collect::<Vec<_>>();
+    let (subgraph, highlight) = match &args[1..] {
+        [mode, adjective, color, ..] => {
+            let bag = Bag { adjective, color };
+            match mode.as_str() {
+                "from" => (Subgraph::From(bag), Some(bag)),
+                "to" => (Subgraph::To(bag), Some(bag)),
+                "highlight" => (Subgraph::All, Some(bag)),
+                _ => {
+                    println!("Expected one of from, to or highlight.");
+                    return;
+                }
+            }
+        }
+        _ => (Subgraph::All, None),
+    };
 
-    println!("{}", count);
+    fs::write(&args[0], to_dot(&rules, subgraph, highlight)).unwrap();
+}
+
+/// Builds the rule graph, printing every problem with the rules if it isn't
+/// valid.
+fn build_graph(data: &str) -> Option<BagGraph<'_>> {
+    match BagGraph::new(parse(data)) {
+        Ok(graph) => Some(graph),
+        Err(errors) => {
+            println!("Invalid rules:");
+            for error in errors {
+                println!("  {}", error);
+            }
+            None
+        }
+    }
 }
 
 fn parse(data: &str) -> impl Iterator<Item = Rule> {
@@ -139,6 +213,12 @@ struct Bag<'a> {
     color: &'a str,
 }
 
+impl fmt::Display for Bag<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}", self.adjective, self.color)
+    }
+}
+
 fn bag(input: &str) -> IResult<&str, Bag> {
     let (input, _) = space0(input)?;
     let (input, adjective) = alpha1(input)?;
@@ -152,7 +232,134 @@ fn bag(input: &str) -> IResult<&str, Bag> {
 
 #[cfg(test)]
 mod tests {
-    use super::{bag, contained, rule, Bag, Contained, Rule};
+    use super::{
+        bag, contained,
+        dot::{to_dot, Subgraph},
+        graph::{BagGraph, RuleError},
+        parse, rule, Bag, Contained, Rule, SHINY_GOLD,
+    };
+
+    const TEST_RULES: &str = include_str!("../data/day_07_test.txt");
+
+    fn bag_named(name: &str) -> Bag<'_> {
+        let mut words = name.split(' ');
+        Bag {
+            adjective: words.next().unwrap(),
+            color: words.next().unwrap(),
+        }
+    }
+
+    #[test]
+    fn graph_finds_containers() {
+        let graph = BagGraph::new(parse(TEST_RULES)).unwrap();
+        let mut containers = graph.containers(SHINY_GOLD).unwrap();
+        containers.sort_by_key(|b| (b.adjective, b.color));
+        assert_eq!(
+            containers,
+            vec![
+                bag_named("bright white"),
+                bag_named("dark orange"),
+                bag_named("light red"),
+                bag_named("muted yellow"),
+            ]
+        );
+    }
+
+    #[test]
+    fn graph_counts_contents() {
+        let graph = BagGraph::new(parse(TEST_RULES)).unwrap();
+        let contents = graph.contents(SHINY_GOLD).unwrap();
+        assert_eq!(contents[&bag_named("dark olive")], 1);
+        assert_eq!(contents[&bag_named("vibrant plum")], 2);
+        assert_eq!(contents[&bag_named("faded blue")], 3 + 10);
+        assert_eq!(contents[&bag_named("dotted black")], 4 + 12);
+        assert_eq!(graph.total_contents(SHINY_GOLD), Some(32));
+    }
+
+    #[test]
+    fn graph_finds_chains() {
+        let graph = BagGraph::new(parse(TEST_RULES)).unwrap();
+        let outer = bag_named("light red");
+        let inner = bag_named("faded blue");
+        assert_eq!(
+            graph.shortest_chain(outer, inner),
+            Some(vec![outer, bag_named("muted yellow"), inner])
+        );
+        assert_eq!(graph.longest_chain(outer, inner).unwrap().len(), 5);
+        assert_eq!(graph.shortest_chain(inner, outer), None);
+    }
+
+    #[test]
+    fn dot_restricts_to_subgraph() {
+        let rules = parse(TEST_RULES).collect::<Vec<_>>();
+        let dot = to_dot(
+            &rules,
+            Subgraph::From(bag_named("dark olive")),
+            Some(bag_named("dark olive")),
+        );
+        assert_eq!(
+            dot,
+            "digraph bags {
+    \"faded blue\";
+    \"dark olive\" [style=filled, fillcolor=gold];
+    \"dotted black\";
+    \"dark olive\" -> \"faded blue\" [label=\"3\"];
+    \"dark olive\" -> \"dotted black\" [label=\"4\"];
+}
+"
+        );
+    }
+
+    #[test]
+    fn dot_includes_containers() {
+        let rules = parse(TEST_RULES).collect::<Vec<_>>();
+        let dot = to_dot(&rules, Subgraph::To(bag_named("bright white")), None);
+        assert_eq!(dot.lines().count(), 2 + 3 + 2);
+        assert!(dot.contains("\"light red\" -> \"bright white\" [label=\"1\"];"));
+        assert!(dot.contains("\"dark orange\" -> \"bright white\" [label=\"3\"];"));
+    }
+
+    #[test]
+    fn graph_rejects_cycles() {
+        let rules = "light red bags contain 1 dark blue bag.
+            dark blue bags contain 2 shiny gold bags, 1 faded blue bag.
+            shiny gold bags contain 1 light red bag.
+            faded blue bags contain no other bags.";
+        assert_eq!(
+            BagGraph::new(parse(rules)),
+            Err(vec![RuleError::Cycle(vec![
+                bag_named("light red"),
+                bag_named("dark blue"),
+                bag_named("shiny gold"),
+                bag_named("light red"),
+            ])])
+        );
+    }
+
+    #[test]
+    fn graph_rejects_undefined_and_duplicate_bags() {
+        let rules = "light red bags contain 1 dark blue bag.
+            light red bags contain no other bags.";
+        assert_eq!(
+            BagGraph::new(parse(rules)),
+            Err(vec![
+                RuleError::Duplicate(bag_named("light red")),
+                RuleError::Undefined(bag_named("dark blue")),
+            ])
+        );
+    }
+
+    #[test]
+    fn graph_finds_unrelated_bags() {
+        let rules = "light red bags contain 1 dark blue bag.
+            dark blue bags contain no other bags.
+            faded blue bags contain no other bags.";
+        let graph = BagGraph::new(parse(rules)).unwrap();
+        assert_eq!(
+            graph.unrelated_to(bag_named("light red")),
+            Some(vec![bag_named("faded blue")])
+        );
+    }
 
     #[test]
     fn rule_parses_empty_contains() {
@@ -0,0 +1,102 @@
+use super::{Bag, Rule};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    fmt::{self, Write},
+};
+
+/// Which part of the rule graph to export.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Subgraph<'a> {
+    All,
+    /// The bag and everything that ends up inside it.
+    From(Bag<'a>),
+    /// The bag and everything that it ends up inside of.
+    To(Bag<'a>),
+}
+
+/// Renders the rules as a Graphviz digraph, with an edge from each container
+/// to the bags it holds labelled by the amount.
+///
+/// This works on the rules directly rather than a validated graph, so rule
+/// sets with cycles or undefined bags can still be drawn.
+pub fn to_dot(rules: &[Rule], subgraph: Subgraph, highlight: Option<Bag>) -> String {
+    let mut dot = String::new();
+    write_dot(&mut dot, rules, subgraph, highlight).unwrap();
+    dot
+}
+
+fn write_dot<W: Write>(
+    out: &mut W,
+    rules: &[Rule],
+    subgraph: Subgraph,
+    highlight: Option<Bag>,
+) -> fmt::Result {
+    let included = match subgraph {
+        Subgraph::All => None,
+        Subgraph::From(bag) => Some(reachable(rules, bag, false)),
+        Subgraph::To(bag) => Some(reachable(rules, bag, true)),
+    };
+    let is_included = |bag: &Bag| match &included {
+        Some(included) => included.contains(bag),
+        None => true,
+    };
+
+    let mut bags = Vec::new();
+    let mut seen = HashSet::new();
+    for rule in rules {
+        let rule_bags = std::iter::once(rule.container).chain(rule.contains.iter().map(|c| c.bag));
+        for bag in rule_bags {
+            if is_included(&bag) && seen.insert(bag) {
+                bags.push(bag);
+            }
+        }
+    }
+
+    writeln!(out, "digraph bags {{")?;
+    for bag in &bags {
+        if Some(*bag) == highlight {
+            writeln!(out, "    \"{}\" [style=filled, fillcolor=gold];", bag)?;
+        } else {
+            writeln!(out, "    \"{}\";", bag)?;
+        }
+    }
+    for rule in rules.iter().filter(|r| is_included(&r.container)) {
+        for contained in rule.contains.iter().filter(|c| is_included(&c.bag)) {
+            writeln!(
+                out,
+                "    \"{}\" -> \"{}\" [label=\"{}\"];",
+                rule.container, contained.bag, contained.amount
+            )?;
+        }
+    }
+    writeln!(out, "}}")
+}
+
+/// The bags reachable from `start` by following containment edges, either
+/// inwards or, if `reverse` is set, outwards.
+fn reachable<'a>(rules: &[Rule<'a>], start: Bag<'a>, reverse: bool) -> HashSet<Bag<'a>> {
+    let mut edges = HashMap::new();
+    for rule in rules {
+        for contained in &rule.contains {
+            let (from, to) = if reverse {
+                (contained.bag, rule.container)
+            } else {
+                (rule.container, contained.bag)
+            };
+            edges.entry(from).or_insert_with(Vec::new).push(to);
+        }
+    }
+
+    let mut seen = HashSet::new();
+    let mut queue = VecDeque::new();
+    seen.insert(start);
+    queue.push_back(start);
+    while let Some(bag) = queue.pop_front() {
+        for next in edges.get(&bag).into_iter().flatten() {
+            if seen.insert(*next) {
+                queue.push_back(*next);
+            }
+        }
+    }
+    seen
+}
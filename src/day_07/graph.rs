@@ -0,0 +1,315 @@
+use super::{Bag, Rule};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    fmt,
+};
+
+/// The containment graph described by a set of rules.
+///
+/// Bags are stored by index; edges point from a container to the bags it
+/// directly holds.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BagGraph<'a> {
+    bags: Vec<Bag<'a>>,
+    indices: HashMap<Bag<'a>, usize>,
+    contents: Vec<Vec<(usize, u64)>>,
+    containers: Vec<Vec<usize>>,
+    order: Vec<usize>,
+}
+
+impl<'a> BagGraph<'a> {
+    /// Builds the graph, checking that every bag is defined exactly once and
+    /// that no bag ends up inside itself.
+    pub fn new<I>(rules: I) -> Result<Self, Vec<RuleError<'a>>>
+    where
+        I: IntoIterator<Item = Rule<'a>>,
+    {
+        let mut graph = Self {
+            bags: Vec::new(),
+            indices: HashMap::new(),
+            contents: Vec::new(),
+            containers: Vec::new(),
+            order: Vec::new(),
+        };
+        let mut errors = Vec::new();
+        let mut defined = Vec::new();
+
+        for rule in rules {
+            let container = graph.index_or_insert(rule.container);
+            defined.resize(graph.bags.len(), false);
+            if defined[container] {
+                errors.push(RuleError::Duplicate(rule.container));
+                continue;
+            }
+            defined[container] = true;
+
+            for contained in rule.contains {
+                let bag = graph.index_or_insert(contained.bag);
+                graph.contents[container].push((bag, u64::from(contained.amount)));
+                graph.containers[bag].push(container);
+            }
+        }
+
+        defined.resize(graph.bags.len(), false);
+        errors.extend(
+            defined
+                .iter()
+                .enumerate()
+                .filter(|(_, d)| !**d)
+                .map(|(i, _)| RuleError::Undefined(graph.bags[i])),
+        );
+
+        graph.order = graph.topological_order();
+        if graph.order.len() < graph.bags.len() {
+            errors.extend(graph.cycles().into_iter().map(RuleError::Cycle));
+        }
+
+        if errors.is_empty() {
+            Ok(graph)
+        } else {
+            Err(errors)
+        }
+    }
+
+    fn index_or_insert(&mut self, bag: Bag<'a>) -> usize {
+        if let Some(index) = self.indices.get(&bag) {
+            return *index;
+        }
+        let index = self.bags.len();
+        self.bags.push(bag);
+        self.indices.insert(bag, index);
+        self.contents.push(Vec::new());
+        self.containers.push(Vec::new());
+        index
+    }
+
+    /// Orders the bags so that every container comes before its contents.
+    fn topological_order(&self) -> Vec<usize> {
+        let mut in_degree = vec![0; self.bags.len()];
+        for (bag, _) in self.contents.iter().flatten() {
+            in_degree[*bag] += 1;
+        }
+
+        let mut queue = (0..self.bags.len())
+            .filter(|i| in_degree[*i] == 0)
+            .collect::<VecDeque<_>>();
+        let mut order = Vec::with_capacity(self.bags.len());
+        while let Some(index) = queue.pop_front() {
+            order.push(index);
+            for (bag, _) in &self.contents[index] {
+                in_degree[*bag] -= 1;
+                if in_degree[*bag] == 0 {
+                    queue.push_back(*bag);
+                }
+            }
+        }
+        order
+    }
+
+    /// Finds a cycle through each edge that leads back into the current
+    /// depth-first search path.
+    fn cycles(&self) -> Vec<Vec<Bag<'a>>> {
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        enum Mark {
+            Unvisited,
+            OnPath,
+            Finished,
+        }
+
+        let mut marks = vec![Mark::Unvisited; self.bags.len()];
+        let mut cycles = Vec::new();
+        for root in 0..self.bags.len() {
+            if marks[root] != Mark::Unvisited {
+                continue;
+            }
+
+            marks[root] = Mark::OnPath;
+            let mut path = vec![(root, 0)];
+            while let Some((index, edge)) = path.last().copied() {
+                let next = match self.contents[index].get(edge) {
+                    Some((next, _)) => *next,
+                    None => {
+                        marks[index] = Mark::Finished;
+                        path.pop();
+                        continue;
+                    }
+                };
+                path.last_mut().unwrap().1 += 1;
+
+                match marks[next] {
+                    Mark::Unvisited => {
+                        marks[next] = Mark::OnPath;
+                        path.push((next, 0));
+                    }
+                    Mark::OnPath => {
+                        let start = path.iter().position(|(i, _)| *i == next).unwrap();
+                        let mut cycle = path[start..]
+                            .iter()
+                            .map(|(i, _)| self.bags[*i])
+                            .collect::<Vec<_>>();
+                        cycle.push(self.bags[next]);
+                        cycles.push(cycle);
+                    }
+                    Mark::Finished => {}
+                }
+            }
+        }
+        cycles
+    }
+
+    /// Every bag that neither contains nor is contained by `bag`.
+    pub fn unrelated_to(&self, bag: Bag<'a>) -> Option<Vec<Bag<'a>>> {
+        let mut related = self.containers(bag)?.into_iter().collect::<HashSet<_>>();
+        related.extend(self.contents(bag)?.keys());
+        related.insert(bag);
+        Some(
+            self.bags
+                .iter()
+                .filter(|b| !related.contains(b))
+                .copied()
+                .collect(),
+        )
+    }
+
+    /// Every bag that eventually contains `bag`.
+    pub fn containers(&self, bag: Bag<'a>) -> Option<Vec<Bag<'a>>> {
+        let start = *self.indices.get(&bag)?;
+        let mut seen = vec![false; self.bags.len()];
+        let mut queue = VecDeque::new();
+        let mut containers = Vec::new();
+        queue.push_back(start);
+        while let Some(index) = queue.pop_front() {
+            for container in &self.containers[index] {
+                if !seen[*container] {
+                    seen[*container] = true;
+                    containers.push(self.bags[*container]);
+                    queue.push_back(*container);
+                }
+            }
+        }
+        Some(containers)
+    }
+
+    /// How many of each bag end up inside `bag`, counting nested bags.
+    pub fn contents(&self, bag: Bag<'a>) -> Option<HashMap<Bag<'a>, u64>> {
+        let start = *self.indices.get(&bag)?;
+        let mut multiplicity = vec![0u64; self.bags.len()];
+        multiplicity[start] = 1;
+        for index in self.order.iter().copied() {
+            if multiplicity[index] == 0 {
+                continue;
+            }
+            for (bag, amount) in &self.contents[index] {
+                multiplicity[*bag] += multiplicity[index] * amount;
+            }
+        }
+        multiplicity[start] = 0;
+
+        Some(
+            multiplicity
+                .iter()
+                .enumerate()
+                .filter(|(_, m)| **m > 0)
+                .map(|(i, m)| (self.bags[i], *m))
+                .collect(),
+        )
+    }
+
+    /// The total number of bags inside `bag`.
+    pub fn total_contents(&self, bag: Bag<'a>) -> Option<u64> {
+        self.contents(bag).map(|c| c.values().sum())
+    }
+
+    /// The chain of bags from `outer` to `inner` with the fewest levels of
+    /// nesting, including both ends.
+    pub fn shortest_chain(&self, outer: Bag<'a>, inner: Bag<'a>) -> Option<Vec<Bag<'a>>> {
+        let start = *self.indices.get(&outer)?;
+        let end = *self.indices.get(&inner)?;
+        let mut parent = vec![None; self.bags.len()];
+        let mut queue = VecDeque::new();
+        parent[start] = Some(start);
+        queue.push_back(start);
+        while let Some(index) = queue.pop_front() {
+            if index == end {
+                return Some(self.chain_to(end, &parent));
+            }
+            for (bag, _) in &self.contents[index] {
+                if parent[*bag].is_none() {
+                    parent[*bag] = Some(index);
+                    queue.push_back(*bag);
+                }
+            }
+        }
+        None
+    }
+
+    /// The chain of bags from `outer` to `inner` with the most levels of
+    /// nesting, including both ends.
+    pub fn longest_chain(&self, outer: Bag<'a>, inner: Bag<'a>) -> Option<Vec<Bag<'a>>> {
+        let start = *self.indices.get(&outer)?;
+        let end = *self.indices.get(&inner)?;
+        let mut depth = vec![None; self.bags.len()];
+        let mut parent = vec![None; self.bags.len()];
+        depth[start] = Some(0);
+        parent[start] = Some(start);
+        for index in self.order.iter().copied() {
+            let d = match depth[index] {
+                Some(d) => d,
+                None => continue,
+            };
+            for (bag, _) in &self.contents[index] {
+                if !matches!(depth[*bag], Some(b) if b > d) {
+                    depth[*bag] = Some(d + 1);
+                    parent[*bag] = Some(index);
+                }
+            }
+        }
+        depth[end]?;
+        Some(self.chain_to(end, &parent))
+    }
+
+    fn chain_to(&self, end: usize, parent: &[Option<usize>]) -> Vec<Bag<'a>> {
+        let mut chain = vec![self.bags[end]];
+        let mut index = end;
+        while let Some(p) = parent[index] {
+            if p == index {
+                break;
+            }
+            chain.push(self.bags[p]);
+            index = p;
+        }
+        chain.reverse();
+        chain
+    }
+}
+
+/// A problem with a rule set that would make queries meaningless.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RuleError<'a> {
+    /// A bag that ends up inside itself, listed from the first bag back
+    /// around to it.
+    Cycle(Vec<Bag<'a>>),
+    /// A bag that is held by another bag but has no rule of its own.
+    Undefined(Bag<'a>),
+    /// A bag with more than one rule.
+    Duplicate(Bag<'a>),
+}
+
+impl fmt::Display for RuleError<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Cycle(bags) => {
+                write!(f, "cycle: ")?;
+                for (i, bag) in bags.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " > ")?;
+                    }
+                    write!(f, "{}", bag)?;
+                }
+                Ok(())
+            }
+            Self::Undefined(bag) => write!(f, "undefined bag: {}", bag),
+            Self::Duplicate(bag) => write!(f, "duplicate rule for: {}", bag),
+        }
+    }
+}
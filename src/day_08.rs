@@ -2,160 +2,238 @@ use nom::{
     branch::alt,
     bytes::complete::tag,
     character::complete::{digit1, space1},
-    combinator::{map_res, value},
+    combinator::{all_consuming, map_res, value},
     IResult,
 };
 
-pub fn star_1(data: String) {
-    let instructions = parse(&data);
-    let mut has_visited = vec![false; instructions.len()];
-    let mut program = ProgramState::new(instructions);
+use std::{
+    fmt,
+    fs::{self, File},
+    io::{self, BufReader},
+};
 
-    loop {
-        if has_visited[program.pc] {
-            break;
-        }
-        has_visited[program.pc] = true;
-        match program.step() {
-            StepResult::Continue => {}
-            StepResult::Terminate => println!("Terminated."),
-            StepResult::InstructionOutOfRange => {
-                println!("Instruction out of range at pc {}", program.pc)
-            }
-        }
+use self::{
+    analysis::Analysis,
+    debugger::Debugger,
+    repair::repairs,
+    vm::{Control, Machine, OpcodeTable, Outcome},
+};
+
+mod analysis;
+mod debugger;
+mod repair;
+mod vm;
+
+const ACC: usize = 0;
+
+pub fn star_1(data: String) {
+    let mut machine = Machine::new(parse(&data), handheld());
+    match machine.run() {
+        Outcome::LoopDetected { .. } => {}
+        Outcome::Terminated => println!("Terminated."),
+        outcome => println!("{:?}", outcome),
     }
 
-    println!("{}", program.acc);
+    println!("{}", machine.registers[ACC]);
 }
 
 pub fn star_2(data: String) {
     let instructions = parse(&data);
-    let instructions_len = instructions.len();
-    let mut program = ProgramState::new(instructions);
+    let repairs = repairs(&instructions);
+    if repairs.is_empty() {
+        println!("No patch terminates.");
+    }
 
-    'a: for i in 0..instructions_len {
-        program.reset();
+    for repair in repairs {
+        let mut program = instructions.clone();
+        program[repair.pc] = repair.instruction;
+        let mut machine = Machine::new(program, handheld());
+        machine.run();
+        println!("acc: {}", machine.registers[ACC]);
+        println!("patch: {:?}", Some((repair.pc, repair.instruction)));
+    }
+}
 
-        let instruction = program.instructions[i];
-        program.patch = match instruction.opcode {
-            Opcode::Nop => Some((
-                i,
-                Instruction {
-                    opcode: Opcode::Jmp,
-                    argument: instruction.argument,
-                },
-            )),
-            Opcode::Jmp => Some((
-                i,
-                Instruction {
-                    opcode: Opcode::Nop,
-                    argument: instruction.argument,
-                },
-            )),
-            Opcode::Acc => continue,
+pub fn run(data: String, args: &[String]) {
+    let mut machine = Machine::new(parse(&data), handheld());
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        machine = match arg.as_str() {
+            "steps" => machine.with_step_limit(args.next().unwrap().parse().unwrap()),
+            "no-loops" => machine.with_loop_detection(false),
+            _ => {
+                println!("Unknown option {}.", arg);
+                return;
+            }
         };
+    }
 
-        let mut has_visited = vec![false; instructions_len];
+    let outcome = machine.run();
+    println!("outcome: {:?}", outcome);
+    println!("steps: {}", machine.steps);
+    println!("acc: {}", machine.registers[ACC]);
+}
 
-        loop {
-            if let Some(hv) = has_visited.get_mut(program.pc) {
-                if *hv {
-                    continue 'a;
-                }
-                *hv = true;
-            }
+pub fn debug(data: String, args: &[String]) {
+    let mut debugger = Debugger::new(Machine::new(parse(&data), handheld()));
+    let stdout = io::stdout();
+    match args.first() {
+        Some(script) => {
+            let script = BufReader::new(File::open(script).unwrap());
+            debugger.run_commands(script, stdout.lock(), false)
+        }
+        None => {
+            let stdin = io::stdin();
+            debugger.run_commands(stdin.lock(), stdout.lock(), true)
+        }
+    }
+    .unwrap();
+}
 
-            match program.step() {
-                StepResult::Continue => {}
-                StepResult::Terminate => break 'a,
-                StepResult::InstructionOutOfRange => continue 'a,
-            }
+pub fn analyse(data: String, args: &[String]) {
+    let program = match assemble(&data) {
+        Ok(program) => program,
+        Err(err) => {
+            println!("{}", err);
+            return;
+        }
+    };
+    let analysis = Analysis::new(&program);
+
+    for (i, block) in analysis.blocks.iter().enumerate() {
+        println!("block {}:", i);
+        for pc in block.clone() {
+            let note = if analysis.reachable[pc] {
+                ""
+            } else {
+                "  (unreachable)"
+            };
+            println!("{:>5}  {}{}", pc, program[pc], note);
         }
     }
+    println!();
+
+    let list = |pcs: &[usize]| match pcs {
+        [] => "none".to_string(),
+        _ => pcs
+            .iter()
+            .map(usize::to_string)
+            .collect::<Vec<_>>()
+            .join(", "),
+    };
+    println!("unreachable: {}", list(&analysis.unreachable()));
+    println!("jumps out of range: {}", list(&analysis.bad_jumps));
+    for cycle in &analysis.loops {
+        println!("loop: {}", list(cycle));
+    }
 
-    println!("acc: {}", program.acc);
-    println!("patch: {:?}", program.patch);
+    if let Some(filename) = args.first() {
+        fs::write(filename, analysis.to_dot(&program)).unwrap();
+    }
+}
+
+pub fn format(data: String, _args: &[String]) {
+    match assemble(&data) {
+        Ok(program) => print!("{}", disassemble(&program)),
+        Err(err) => println!("{}", err),
+    }
 }
 
 fn parse(input: &str) -> Vec<Instruction> {
+    assemble(input).unwrap()
+}
+
+/// Parses a program, one instruction per line, ignoring blank lines.
+fn assemble(input: &str) -> Result<Vec<Instruction>, AssembleError> {
     input
         .lines()
-        .map(str::trim)
-        .filter(|s| !s.is_empty())
-        .map(|s| instruction(s).unwrap().1)
+        .enumerate()
+        .map(|(i, s)| (i, s.trim()))
+        .filter(|(_, s)| !s.is_empty())
+        .map(|(i, s)| match all_consuming(instruction)(s) {
+            Ok((_, instruction)) => Ok(instruction),
+            Err(_) => Err(AssembleError {
+                line: i + 1,
+                text: s.to_string(),
+            }),
+        })
         .collect()
 }
 
-#[derive(Debug, Clone)]
-struct ProgramState {
-    pc: usize,
-    acc: i32,
-    instructions: Vec<Instruction>,
-    patch: Option<(usize, Instruction)>,
+fn disassemble(program: &[Instruction]) -> String {
+    program.iter().map(|i| format!("{}\n", i)).collect()
 }
 
-impl ProgramState {
-    fn new(instructions: Vec<Instruction>) -> Self {
-        Self {
-            pc: 0,
-            acc: 0,
-            instructions,
-            patch: None,
-        }
-    }
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct AssembleError {
+    line: usize,
+    text: String,
+}
 
-    fn reset(&mut self) {
-        self.pc = 0;
-        self.acc = 0;
+impl fmt::Display for AssembleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: invalid instruction `{}`", self.line, self.text)
     }
+}
 
-    fn get_instruction(&self) -> Option<Instruction> {
-        if let Some((pc, instruction)) = self.patch {
-            if self.pc == pc {
-                return Some(instruction);
-            }
-        }
-
-        self.instructions.get(self.pc).copied()
-    }
+/// The handheld's instruction set, which keeps the accumulator in the only
+/// register.
+fn handheld() -> OpcodeTable<Instruction> {
+    OpcodeTable::new(1)
+        .with(Opcode::Nop, |_, _| Control::Next)
+        .with(Opcode::Acc, |registers, ins: Instruction| {
+            registers[ACC] += i64::from(ins.argument);
+            Control::Next
+        })
+        .with(Opcode::Jmp, |_, ins: Instruction| {
+            Control::Jump(i64::from(ins.argument))
+        })
+}
 
-    fn step(&mut self) -> StepResult {
-        if self.pc == self.instructions.len() {
-            return StepResult::Terminate;
-        }
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Instruction {
+    opcode: Opcode,
+    argument: i32,
+}
 
-        let instruction = match self.get_instruction() {
-            Some(i) => i,
-            None => return StepResult::InstructionOutOfRange,
+impl Instruction {
+    /// Swaps a `nop` for a `jmp` or vice versa.
+    fn flipped(self) -> Option<Self> {
+        let opcode = match self.opcode {
+            Opcode::Nop => Opcode::Jmp,
+            Opcode::Jmp => Opcode::Nop,
+            Opcode::Acc => return None,
         };
+        Some(Self { opcode, ..self })
+    }
 
-        match instruction.opcode {
-            Opcode::Nop => self.pc += 1,
-            Opcode::Acc => {
-                self.acc += instruction.argument;
-                self.pc += 1;
-            }
-            Opcode::Jmp => {
-                let next_pc = self.pc as isize + instruction.argument as isize;
-                self.pc = next_pc as usize;
-            }
+    /// The address of the instruction that runs after this one, if it is
+    /// inside the program or just past its end.
+    fn next_pc(self, pc: usize, len: usize) -> Option<usize> {
+        let next = match self.opcode {
+            Opcode::Nop | Opcode::Acc => pc as i64 + 1,
+            Opcode::Jmp => pc as i64 + i64::from(self.argument),
+        };
+        if next >= 0 && next <= len as i64 {
+            Some(next as usize)
+        } else {
+            None
         }
-
-        StepResult::Continue
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-enum StepResult {
-    Continue,
-    Terminate,
-    InstructionOutOfRange,
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {:+}", self.opcode, self.argument)
+    }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-struct Instruction {
-    opcode: Opcode,
-    argument: i32,
+impl vm::Instruction for Instruction {
+    type Opcode = Opcode;
+
+    fn opcode(&self) -> Opcode {
+        self.opcode
+    }
 }
 
 fn instruction(input: &str) -> IResult<&str, Instruction> {
@@ -171,13 +249,24 @@ fn argument(input: &str) -> IResult<&str, i32> {
     Ok((input, sign * amount as i32))
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 enum Opcode {
     Nop,
     Acc,
     Jmp,
 }
 
+impl fmt::Display for Opcode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Self::Nop => "nop",
+            Self::Acc => "acc",
+            Self::Jmp => "jmp",
+        };
+        write!(f, "{}", name)
+    }
+}
+
 fn opcode(input: &str) -> IResult<&str, Opcode> {
     alt((
         value(Opcode::Nop, tag("nop")),
@@ -188,7 +277,223 @@ fn opcode(input: &str) -> IResult<&str, Opcode> {
 
 #[cfg(test)]
 mod tests {
-    use super::{instruction, Instruction, Opcode};
+    use super::{
+        analysis::Analysis,
+        assemble,
+        debugger::{Breakpoint, Debugger},
+        disassemble, handheld, instruction, parse,
+        repair::{repairs, Repair},
+        vm::{self, Control, Machine, OpcodeTable, Outcome},
+        Instruction, Opcode, ACC,
+    };
+
+    const TEST_PROGRAM: &str = include_str!("../data/day_08_test.txt");
+
+    #[test]
+    fn machine_detects_loop() {
+        let mut machine = Machine::new(parse(TEST_PROGRAM), handheld());
+        assert_eq!(machine.run(), Outcome::LoopDetected { pc: 1 });
+        assert_eq!(machine.registers[ACC], 5);
+    }
+
+    #[test]
+    fn machine_stops_at_step_limit() {
+        let mut machine = Machine::new(parse(TEST_PROGRAM), handheld())
+            .with_loop_detection(false)
+            .with_step_limit(100);
+        assert_eq!(machine.run(), Outcome::StepLimit);
+        assert_eq!(machine.steps, 100);
+    }
+
+    #[test]
+    fn machine_terminates_patched_program() {
+        let mut program = parse(TEST_PROGRAM);
+        program[7] = program[7].flipped().unwrap();
+        let mut machine = Machine::new(program, handheld());
+        assert_eq!(machine.run(), Outcome::Terminated);
+        assert_eq!(machine.registers[ACC], 8);
+    }
+
+    #[test]
+    fn machine_reports_out_of_range_jump() {
+        let mut machine = Machine::new(parse("nop +0\njmp -2"), handheld());
+        assert_eq!(machine.run(), Outcome::OutOfRange { pc: -1 });
+    }
+
+    #[test]
+    fn disassemble_round_trips() {
+        let program = assemble(TEST_PROGRAM).unwrap();
+        assert_eq!(disassemble(&program), TEST_PROGRAM);
+    }
+
+    #[test]
+    fn assemble_reports_bad_line() {
+        let err = assemble("nop +0\n\njmp 4\n").unwrap_err();
+        assert_eq!(err.to_string(), "line 3: invalid instruction `jmp 4`");
+    }
+
+    #[test]
+    fn analysis_finds_structure() {
+        let program = parse(TEST_PROGRAM);
+        let analysis = Analysis::new(&program);
+        assert_eq!(analysis.unreachable(), vec![5, 8]);
+        assert_eq!(analysis.bad_jumps, Vec::<usize>::new());
+        assert_eq!(analysis.blocks, vec![0..1, 1..3, 3..5, 5..6, 6..8, 8..9]);
+        assert_eq!(analysis.loops, vec![vec![1, 2, 6, 7, 3, 4]]);
+    }
+
+    #[test]
+    fn analysis_finds_bad_jumps_and_separate_loops() {
+        let program = parse("jmp +2\njmp +0\njmp +5\njmp -4");
+        let analysis = Analysis::new(&program);
+        assert_eq!(analysis.bad_jumps, vec![2, 3]);
+        assert_eq!(analysis.unreachable(), vec![1, 3]);
+        assert_eq!(analysis.loops, vec![vec![1]]);
+    }
+
+    #[test]
+    fn analysis_exports_dot() {
+        let program = parse("nop +0\njmp +2\nacc +1\njmp -3");
+        let dot = Analysis::new(&program).to_dot(&program);
+        assert_eq!(
+            dot,
+            "digraph program {
+    node [shape=box, fontname=monospace];
+    b0 [label=\"0: nop +0\\l1: jmp +2\\l\"];
+    b1 [label=\"2: acc +1\\l\", style=filled, fillcolor=lightgrey];
+    b2 [label=\"3: jmp -3\\l\"];
+    end [shape=doublecircle];
+    b0 -> b2;
+    b1 -> b2;
+    b2 -> b0;
+}
+"
+        );
+    }
+
+    #[test]
+    fn repairs_finds_flip() {
+        let program = parse(TEST_PROGRAM);
+        assert_eq!(
+            repairs(&program),
+            vec![Repair {
+                pc: 7,
+                instruction: Instruction {
+                    opcode: Opcode::Nop,
+                    argument: -4,
+                },
+            }]
+        );
+    }
+
+    #[test]
+    fn repairs_finds_every_flip() {
+        let program = parse("nop +3\njmp +0\nacc +1");
+        let repairs = repairs(&program);
+        assert_eq!(repairs.iter().map(|r| r.pc).collect::<Vec<_>>(), vec![0, 1]);
+    }
+
+    #[test]
+    fn repairs_is_empty_for_terminating_program() {
+        assert_eq!(repairs(&parse("nop +0\nacc +1")), vec![]);
+    }
+
+    #[test]
+    fn debugger_stops_at_breakpoints() {
+        let mut debugger = Debugger::new(Machine::new(parse(TEST_PROGRAM), handheld()));
+        debugger.add_breakpoint(Breakpoint::Opcode(Opcode::Jmp));
+        assert_eq!(debugger.resume(), None);
+        assert_eq!(debugger.trace().len(), 2);
+        assert_eq!(debugger.acc(), 1);
+
+        debugger.clear_breakpoints();
+        debugger.add_breakpoint(Breakpoint::Pc(6));
+        assert_eq!(debugger.resume(), None);
+        assert_eq!(debugger.trace().last().unwrap().pc, 2);
+        assert_eq!(debugger.resume(), Some(Outcome::LoopDetected { pc: 1 }));
+        assert_eq!(debugger.acc(), 5);
+    }
+
+    #[test]
+    fn debugger_runs_script() {
+        let mut debugger = Debugger::new(Machine::new(parse(TEST_PROGRAM), handheld()));
+        let script = "break 3\ncontinue\nacc\nstep 2\ntrace\ntrace json\nquit\nacc\n";
+        let mut output = Vec::new();
+        debugger
+            .run_commands(script.as_bytes(), &mut output, false)
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "pc 3: acc +3
+acc = 2
+pc 1: acc +1
+    0  nop +0   acc = 0
+    1  acc +1   acc = 1
+    2  jmp +4   acc = 1
+    6  acc +1   acc = 2
+    7  jmp -4   acc = 2
+    3  acc +3   acc = 5
+    4  jmp -3   acc = 5
+[{\"pc\":0,\"instruction\":\"nop +0\",\"acc\":0},\
+{\"pc\":1,\"instruction\":\"acc +1\",\"acc\":1},\
+{\"pc\":2,\"instruction\":\"jmp +4\",\"acc\":1},\
+{\"pc\":6,\"instruction\":\"acc +1\",\"acc\":2},\
+{\"pc\":7,\"instruction\":\"jmp -4\",\"acc\":2},\
+{\"pc\":3,\"instruction\":\"acc +3\",\"acc\":5},\
+{\"pc\":4,\"instruction\":\"jmp -3\",\"acc\":5}]
+"
+        );
+    }
+
+    #[test]
+    fn machine_runs_custom_instruction_set() {
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        enum Op {
+            Inc(usize),
+            Jnz(usize),
+            Hlt,
+        }
+
+        #[derive(Debug, Clone, Copy)]
+        struct Ins(Op, i64);
+
+        impl vm::Instruction for Ins {
+            type Opcode = Op;
+
+            fn opcode(&self) -> Op {
+                self.0
+            }
+        }
+
+        let table = OpcodeTable::new(2)
+            .with(Op::Inc(0), |r, Ins(_, x)| {
+                r[0] += x;
+                Control::Next
+            })
+            .with(Op::Inc(1), |r, Ins(_, x)| {
+                r[1] += x;
+                Control::Next
+            })
+            .with(Op::Jnz(0), |r, Ins(_, x)| {
+                if r[0] != 0 {
+                    Control::Jump(x)
+                } else {
+                    Control::Next
+                }
+            })
+            .with(Op::Hlt, |_, _| Control::Halt);
+        let program = vec![
+            Ins(Op::Inc(0), 3),
+            Ins(Op::Inc(1), 2),
+            Ins(Op::Inc(0), -1),
+            Ins(Op::Jnz(0), -2),
+            Ins(Op::Hlt, 0),
+            Ins(Op::Inc(1), 100),
+        ];
+        let mut machine = Machine::new(program, table).with_loop_detection(false);
+        assert_eq!(machine.run(), Outcome::Halted);
+        assert_eq!(machine.registers, vec![0, 6]);
+    }
 
     #[test]
     fn instruction_parses_nop() {
@@ -0,0 +1,128 @@
+use super::{Instruction, Opcode};
+use std::{
+    fmt::{self, Write},
+    ops::Range,
+};
+
+/// What can be learned about a program without running it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Analysis {
+    /// Whether each instruction can run when starting from the beginning.
+    pub reachable: Vec<bool>,
+    /// Jumps whose target is neither in the program nor just past its end.
+    pub bad_jumps: Vec<usize>,
+    /// Runs of instructions that are only entered at the start.
+    pub blocks: Vec<Range<usize>>,
+    /// The instructions in each cycle of the control flow graph, starting
+    /// from the lowest address.
+    pub loops: Vec<Vec<usize>>,
+}
+
+impl Analysis {
+    pub fn new(program: &[Instruction]) -> Self {
+        let len = program.len();
+        let next = |pc: usize| program[pc].next_pc(pc, len).filter(|n| *n < len);
+
+        let mut reachable = vec![false; len];
+        let mut pc = Some(0).filter(|_| len > 0);
+        while let Some(p) = pc.filter(|p| !reachable[*p]) {
+            reachable[p] = true;
+            pc = next(p);
+        }
+
+        let bad_jumps = (0..len)
+            .filter(|pc| program[*pc].next_pc(*pc, len).is_none())
+            .collect();
+
+        let mut leaders = vec![false; len + 1];
+        leaders[0] = true;
+        leaders[len] = true;
+        for (pc, instruction) in program.iter().enumerate() {
+            if instruction.opcode == Opcode::Jmp {
+                leaders[pc + 1] = true;
+                if let Some(target) = instruction.next_pc(pc, len) {
+                    leaders[target] = true;
+                }
+            }
+        }
+        let starts = (0..=len).filter(|pc| leaders[*pc]).collect::<Vec<_>>();
+        let blocks = starts.windows(2).map(|w| w[0]..w[1]).collect();
+
+        // Every instruction has at most one successor, so following them
+        // from each unexplored instruction either finds a new cycle or runs
+        // into something already explored.
+        let mut loops = Vec::new();
+        let mut explored = vec![false; len];
+        for start in 0..len {
+            let mut path = Vec::new();
+            let mut pc = Some(start);
+            while let Some(p) = pc.filter(|p| !explored[*p]) {
+                explored[p] = true;
+                path.push(p);
+                pc = next(p);
+            }
+            if let Some(p) = pc {
+                if let Some(i) = path.iter().position(|x| *x == p) {
+                    let mut cycle = path.split_off(i);
+                    let min = (0..cycle.len()).min_by_key(|i| cycle[*i]).unwrap();
+                    cycle.rotate_left(min);
+                    loops.push(cycle);
+                }
+            }
+        }
+
+        Self {
+            reachable,
+            bad_jumps,
+            blocks,
+            loops,
+        }
+    }
+
+    pub fn unreachable(&self) -> Vec<usize> {
+        (0..self.reachable.len())
+            .filter(|pc| !self.reachable[*pc])
+            .collect()
+    }
+
+    fn block_at(&self, pc: usize) -> Option<usize> {
+        self.blocks.iter().position(|b| b.contains(&pc))
+    }
+
+    /// Renders the control flow graph of basic blocks as a Graphviz
+    /// digraph, with unreachable blocks greyed out.
+    pub fn to_dot(&self, program: &[Instruction]) -> String {
+        let mut dot = String::new();
+        self.write_dot(&mut dot, program).unwrap();
+        dot
+    }
+
+    fn write_dot<W: Write>(&self, out: &mut W, program: &[Instruction]) -> fmt::Result {
+        let len = program.len();
+        writeln!(out, "digraph program {{")?;
+        writeln!(out, "    node [shape=box, fontname=monospace];")?;
+        for (i, block) in self.blocks.iter().enumerate() {
+            let mut label = String::new();
+            for pc in block.clone() {
+                write!(label, "{}: {}\\l", pc, program[pc])?;
+            }
+            let style = if self.reachable[block.start] {
+                ""
+            } else {
+                ", style=filled, fillcolor=lightgrey"
+            };
+            writeln!(out, "    b{} [label=\"{}\"{}];", i, label, style)?;
+        }
+        writeln!(out, "    end [shape=doublecircle];")?;
+
+        for (i, block) in self.blocks.iter().enumerate() {
+            let last = block.end - 1;
+            match program[last].next_pc(last, len) {
+                Some(next) if next == len => writeln!(out, "    b{} -> end;", i)?,
+                Some(next) => writeln!(out, "    b{} -> b{};", i, self.block_at(next).unwrap())?,
+                None => {}
+            }
+        }
+        writeln!(out, "}}")
+    }
+}
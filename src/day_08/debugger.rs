@@ -0,0 +1,178 @@
+use super::{
+    opcode,
+    vm::{Instruction as _, Machine, Outcome, TraceEntry},
+    Instruction, Opcode, ACC,
+};
+use std::{
+    fmt::Write as _,
+    io::{self, BufRead, Write},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Breakpoint {
+    Pc(usize),
+    Opcode(Opcode),
+}
+
+impl Breakpoint {
+    fn parse(input: &str) -> Option<Self> {
+        if let Ok(pc) = input.parse() {
+            return Some(Self::Pc(pc));
+        }
+        match opcode(input) {
+            Ok(("", opcode)) => Some(Self::Opcode(opcode)),
+            _ => None,
+        }
+    }
+}
+
+/// Steps through a handheld program, stopping at breakpoints.
+#[derive(Debug, Clone)]
+pub struct Debugger {
+    machine: Machine<Instruction>,
+    breakpoints: Vec<Breakpoint>,
+    outcome: Option<Outcome>,
+}
+
+impl Debugger {
+    pub fn new(machine: Machine<Instruction>) -> Self {
+        Self {
+            machine: machine.with_trace(),
+            breakpoints: Vec::new(),
+            outcome: None,
+        }
+    }
+
+    pub fn add_breakpoint(&mut self, breakpoint: Breakpoint) {
+        if !self.breakpoints.contains(&breakpoint) {
+            self.breakpoints.push(breakpoint);
+        }
+    }
+
+    pub fn clear_breakpoints(&mut self) {
+        self.breakpoints.clear();
+    }
+
+    pub fn acc(&self) -> i64 {
+        self.machine.registers[ACC]
+    }
+
+    pub fn trace(&self) -> &[TraceEntry<Instruction>] {
+        self.machine.trace().unwrap()
+    }
+
+    /// Runs one instruction, unless the machine has already stopped.
+    pub fn step(&mut self) -> Option<Outcome> {
+        if self.outcome.is_none() {
+            self.outcome = self.machine.step();
+        }
+        self.outcome
+    }
+
+    /// Runs until the next instruction is at a breakpoint or the machine
+    /// stops. Always runs at least one instruction.
+    pub fn resume(&mut self) -> Option<Outcome> {
+        while self.step().is_none() {
+            if self.at_breakpoint() {
+                break;
+            }
+        }
+        self.outcome
+    }
+
+    fn at_breakpoint(&self) -> bool {
+        let pc = self.machine.pc;
+        let opcode = self.machine.current().map(|i| i.opcode());
+        self.breakpoints.iter().any(|b| match b {
+            Breakpoint::Pc(p) => *p == pc,
+            Breakpoint::Opcode(o) => Some(*o) == opcode,
+        })
+    }
+
+    /// Describes where the machine is stopped.
+    fn status(&self) -> String {
+        match (self.outcome, self.machine.current()) {
+            (Some(outcome), _) => format!("stopped: {:?}", outcome),
+            (None, Some(next)) => format!("pc {}: {}", self.machine.pc, next),
+            (None, None) => format!("pc {}", self.machine.pc),
+        }
+    }
+
+    pub fn trace_text(&self) -> String {
+        let mut text = String::new();
+        for entry in self.trace() {
+            writeln!(
+                text,
+                "{:>5}  {:<8} acc = {}",
+                entry.pc,
+                entry.instruction.to_string(),
+                entry.registers[ACC]
+            )
+            .unwrap();
+        }
+        text
+    }
+
+    pub fn trace_json(&self) -> String {
+        let entries = self
+            .trace()
+            .iter()
+            .map(|entry| {
+                format!(
+                    "{{\"pc\":{},\"instruction\":\"{}\",\"acc\":{}}}",
+                    entry.pc, entry.instruction, entry.registers[ACC]
+                )
+            })
+            .collect::<Vec<_>>();
+        format!("[{}]\n", entries.join(","))
+    }
+
+    /// Runs debugger commands from `input` until it ends or a `quit`
+    /// command, writing responses to `output`.
+    pub fn run_commands<R, W>(&mut self, input: R, mut output: W, prompt: bool) -> io::Result<()>
+    where
+        R: BufRead,
+        W: Write,
+    {
+        if prompt {
+            write!(output, "(dbg) ")?;
+            output.flush()?;
+        }
+        for line in input.lines() {
+            let line = line?;
+            let words = line.split_whitespace().collect::<Vec<_>>();
+            match words[..] {
+                [] => {}
+                ["break", at] => match Breakpoint::parse(at) {
+                    Some(breakpoint) => self.add_breakpoint(breakpoint),
+                    None => writeln!(output, "Expected a pc or opcode.")?,
+                },
+                ["clear"] => self.clear_breakpoints(),
+                ["step"] => {
+                    self.step();
+                    writeln!(output, "{}", self.status())?;
+                }
+                ["step", count] => {
+                    for _ in 0..count.parse().unwrap_or(1) {
+                        self.step();
+                    }
+                    writeln!(output, "{}", self.status())?;
+                }
+                ["continue"] => {
+                    self.resume();
+                    writeln!(output, "{}", self.status())?;
+                }
+                ["acc"] => writeln!(output, "acc = {}", self.acc())?,
+                ["trace"] => write!(output, "{}", self.trace_text())?,
+                ["trace", "json"] => write!(output, "{}", self.trace_json())?,
+                ["quit"] => return Ok(()),
+                _ => writeln!(output, "Unknown command: {}", line)?,
+            }
+            if prompt {
+                write!(output, "(dbg) ")?;
+                output.flush()?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,61 @@
+use super::Instruction;
+
+/// Replacing the instruction at `pc` with `instruction` makes the program
+/// terminate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Repair {
+    pub pc: usize,
+    pub instruction: Instruction,
+}
+
+/// Finds every single `nop`/`jmp` flip that makes the program terminate.
+///
+/// Rather than re-running the program once per flip, this works out which
+/// instructions already lead to the end of the program by walking the
+/// control flow graph backwards from it. Only instructions on the original
+/// path can change what the program does, so a flip works exactly when it
+/// sends one of them into that set.
+pub fn repairs(program: &[Instruction]) -> Vec<Repair> {
+    let len = program.len();
+
+    let mut predecessors = vec![Vec::new(); len + 1];
+    for (pc, instruction) in program.iter().enumerate() {
+        if let Some(next) = instruction.next_pc(pc, len) {
+            predecessors[next].push(pc);
+        }
+    }
+
+    let mut terminates = vec![false; len + 1];
+    let mut stack = vec![len];
+    terminates[len] = true;
+    while let Some(pc) = stack.pop() {
+        for prev in &predecessors[pc] {
+            if !terminates[*prev] {
+                terminates[*prev] = true;
+                stack.push(*prev);
+            }
+        }
+    }
+
+    if terminates[0] {
+        return Vec::new();
+    }
+
+    let mut repairs = Vec::new();
+    let mut visited = vec![false; len];
+    let mut pc = Some(0);
+    while let Some(p) = pc.filter(|p| *p < len && !visited[*p]) {
+        visited[p] = true;
+        let instruction = program[p];
+        if let Some(flipped) = instruction.flipped() {
+            if flipped.next_pc(p, len).is_some_and(|n| terminates[n]) {
+                repairs.push(Repair {
+                    pc: p,
+                    instruction: flipped,
+                });
+            }
+        }
+        pc = instruction.next_pc(p, len);
+    }
+    repairs
+}
@@ -0,0 +1,180 @@
+use std::{collections::HashMap, fmt, hash::Hash};
+
+/// An instruction that the machine can look up in its opcode table.
+pub trait Instruction: Copy {
+    type Opcode: Copy + Eq + Hash + fmt::Debug;
+
+    fn opcode(&self) -> Self::Opcode;
+}
+
+/// The effect of an operation on the flow of control.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Control {
+    Next,
+    Jump(i64),
+    /// Stops the machine. The handheld has no instruction for this, but
+    /// other instruction sets built on the machine can.
+    #[allow(dead_code)]
+    Halt,
+}
+
+/// Carries out an instruction against the registers.
+pub type Operation<I> = fn(&mut [i64], I) -> Control;
+
+/// Maps each opcode to the operation that implements it, along with the
+/// number of registers the operations use.
+#[derive(Debug, Clone)]
+pub struct OpcodeTable<I: Instruction> {
+    registers: usize,
+    ops: HashMap<I::Opcode, Operation<I>>,
+}
+
+impl<I: Instruction> OpcodeTable<I> {
+    pub fn new(registers: usize) -> Self {
+        Self {
+            registers,
+            ops: HashMap::new(),
+        }
+    }
+
+    pub fn with(mut self, opcode: I::Opcode, op: Operation<I>) -> Self {
+        self.ops.insert(opcode, op);
+        self
+    }
+}
+
+/// Why the machine stopped running.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    /// Execution ran off the end of the program.
+    Terminated,
+    /// An operation asked to halt.
+    Halted,
+    /// The instruction at `pc` was about to run a second time.
+    LoopDetected { pc: usize },
+    /// Execution jumped to an address outside the program.
+    OutOfRange { pc: i64 },
+    /// The step limit was reached.
+    StepLimit,
+    /// There's no operation for the instruction at `pc`.
+    UnknownOpcode { pc: usize },
+}
+
+/// The state of the machine after running an instruction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceEntry<I> {
+    pub pc: usize,
+    pub instruction: I,
+    pub registers: Vec<i64>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Machine<I: Instruction> {
+    pub pc: usize,
+    pub registers: Vec<i64>,
+    pub steps: usize,
+    program: Vec<I>,
+    table: OpcodeTable<I>,
+    visited: Vec<bool>,
+    detect_loops: bool,
+    step_limit: Option<usize>,
+    trace: Option<Vec<TraceEntry<I>>>,
+}
+
+impl<I: Instruction> Machine<I> {
+    /// Creates a machine which detects loops and has no step limit.
+    pub fn new(program: Vec<I>, table: OpcodeTable<I>) -> Self {
+        let len = program.len();
+        Self {
+            pc: 0,
+            registers: vec![0; table.registers],
+            steps: 0,
+            program,
+            table,
+            visited: vec![false; len],
+            detect_loops: true,
+            step_limit: None,
+            trace: None,
+        }
+    }
+
+    pub fn with_loop_detection(mut self, detect_loops: bool) -> Self {
+        self.detect_loops = detect_loops;
+        self
+    }
+
+    pub fn with_step_limit(mut self, limit: usize) -> Self {
+        self.step_limit = Some(limit);
+        self
+    }
+
+    /// Records every instruction the machine runs.
+    pub fn with_trace(mut self) -> Self {
+        self.trace = Some(Vec::new());
+        self
+    }
+
+    pub fn trace(&self) -> Option<&[TraceEntry<I>]> {
+        self.trace.as_deref()
+    }
+
+    /// The instruction that will run next, if `pc` is inside the program.
+    pub fn current(&self) -> Option<I> {
+        self.program.get(self.pc).copied()
+    }
+
+    /// Runs a single instruction, returning the outcome if the machine
+    /// stops.
+    pub fn step(&mut self) -> Option<Outcome> {
+        if self.pc == self.program.len() {
+            return Some(Outcome::Terminated);
+        }
+        if self.step_limit == Some(self.steps) {
+            return Some(Outcome::StepLimit);
+        }
+        if self.detect_loops {
+            if self.visited[self.pc] {
+                return Some(Outcome::LoopDetected { pc: self.pc });
+            }
+            self.visited[self.pc] = true;
+        }
+
+        let instruction = self.program[self.pc];
+        let op = match self.table.ops.get(&instruction.opcode()) {
+            Some(op) => op,
+            None => return Some(Outcome::UnknownOpcode { pc: self.pc }),
+        };
+        self.steps += 1;
+
+        let control = op(&mut self.registers, instruction);
+        if let Some(trace) = &mut self.trace {
+            trace.push(TraceEntry {
+                pc: self.pc,
+                instruction,
+                registers: self.registers.clone(),
+            });
+        }
+
+        match control {
+            Control::Next => self.pc += 1,
+            Control::Jump(offset) => {
+                let next_pc = self.pc as i64 + offset;
+                if next_pc < 0 || next_pc > self.program.len() as i64 {
+                    return Some(Outcome::OutOfRange { pc: next_pc });
+                }
+                self.pc = next_pc as usize;
+            }
+            Control::Halt => return Some(Outcome::Halted),
+        }
+
+        None
+    }
+
+    pub fn run(&mut self) -> Outcome {
+        loop {
+            if let Some(outcome) = self.step() {
+                return outcome;
+            }
+        }
+    }
+}
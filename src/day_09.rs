@@ -1,167 +1,232 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    ops::Range,
+};
+
+const PREAMBLE: usize = 25;
+
 pub fn star_1(data: String) {
-    let nums = parse(&data).collect::<Vec<_>>();
-    let num = invalid_num(&nums, 26);
-    println!("{}", num);
+    match invalid_numbers(parse(&data), PREAMBLE).next() {
+        Some((_, num)) => println!("{}", num),
+        None => println!("All numbers are valid."),
+    }
 }
 
 pub fn star_2(data: String) {
     let nums = parse(&data).collect::<Vec<_>>();
-    let num = invalid_num(&nums, 26);
-    let slice = nums
-        .contiguous_slices()
-        .filter(|s| s.len() < 2)
-        .find(|s| s.iter().sum::<u64>() == num)
-        .unwrap();
-    let max = slice.iter().max().unwrap();
-    let min = slice.iter().min().unwrap();
-    let weakness = max + min;
-    println!("{}", weakness);
+    let num = match invalid_numbers(nums.iter().copied(), PREAMBLE).next() {
+        Some((_, num)) => num,
+        None => {
+            println!("All numbers are valid.");
+            return;
+        }
+    };
+    match SummingRanges::new(&nums, num).next() {
+        Some(range) => println!("{}", weakness(&nums[range])),
+        None => println!("No range of numbers sums to {}.", num),
+    }
 }
 
-fn invalid_num(nums: &[u64], window_size: usize) -> u64 {
-    nums.windows(window_size)
-        .filter_map(|n| {
-            let prev = &n[..n.len() - 1];
-            let num = &n[n.len() - 1];
-            if prev.all_pairs().filter(|(a, b)| *a + *b == *num).count() >= 1 {
-                None
-            } else {
-                Some(*num)
-            }
-        })
-        .next()
-        .unwrap()
+pub fn invalid(data: String, args: &[String]) {
+    let preamble = args.first().map_or(PREAMBLE, |p| p.parse().unwrap());
+    for (index, num) in invalid_numbers(parse(&data), preamble) {
+        println!("{}: {}", index, num);
+    }
 }
 
-fn parse<'a>(data: &'a str) -> impl Iterator<Item = u64> + 'a {
-    data.lines()
-        .map(str::trim)
-        .filter(|s| !s.is_empty())
-        .map(|s| s.parse::<u64>().unwrap())
+pub fn weaknesses(data: String, args: &[String]) {
+    let preamble = args.first().map_or(PREAMBLE, |p| p.parse().unwrap());
+    let nums = parse(&data).collect::<Vec<_>>();
+    for (index, num) in invalid_numbers(nums.iter().copied(), preamble) {
+        for range in SummingRanges::new(&nums, num) {
+            println!(
+                "{} at {}: {}..{} gives {}",
+                num,
+                index,
+                range.start,
+                range.end,
+                weakness(&nums[range.clone()])
+            );
+        }
+    }
+}
+
+fn weakness(nums: &[u64]) -> u64 {
+    nums.iter().min().unwrap() + nums.iter().max().unwrap()
 }
 
-struct AllPairs<'a, T> {
-    first: usize,
-    second: usize,
-    slice: &'a [T],
+/// Checks each number against the sums of pairs of the `preamble` numbers
+/// before it.
+///
+/// The sums of every pair in the window are kept in a multiset, so each new
+/// number is checked in constant time and updating the window costs
+/// O(`preamble`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Validator {
+    preamble: usize,
+    window: VecDeque<u64>,
+    sums: HashMap<u64, usize>,
 }
 
-impl<'a, T> AllPairs<'a, T> {
-    fn new(slice: &'a [T]) -> Self {
+impl Validator {
+    fn new(preamble: usize) -> Self {
         Self {
-            first: 0,
-            second: 1,
-            slice,
+            preamble,
+            window: VecDeque::with_capacity(preamble),
+            sums: HashMap::new(),
         }
     }
-}
 
-impl<'a, T> Iterator for AllPairs<'a, T> {
-    type Item = (&'a T, &'a T);
-
-    fn next(&mut self) -> Option<Self::Item> {
-        if self.second >= self.slice.len() {
-            self.first += 1;
-            self.second = self.first + 1;
+    /// Adds the next number, returning whether it is valid, or `None` if it
+    /// is part of the preamble.
+    fn push(&mut self, num: u64) -> Option<bool> {
+        let is_valid = if self.window.len() < self.preamble {
+            None
+        } else {
+            Some(self.sums.contains_key(&num))
+        };
+
+        if self.window.len() == self.preamble {
+            if let Some(old) = self.window.pop_front() {
+                for other in &self.window {
+                    let sum = self.sums.get_mut(&(old + other)).unwrap();
+                    *sum -= 1;
+                    if *sum == 0 {
+                        self.sums.remove(&(old + other));
+                    }
+                }
+            }
         }
-
-        if self.second >= self.slice.len() {
-            return None;
+        if self.preamble > 0 {
+            for other in &self.window {
+                *self.sums.entry(num + other).or_insert(0) += 1;
+            }
+            self.window.push_back(num);
         }
 
-        let pair = (&self.slice[self.first], &self.slice[self.second]);
-        self.second += 1;
-        Some(pair)
+        is_valid
     }
 }
 
-struct ContiguousSlices<'a, T> {
+/// Every number that isn't the sum of two of the `preamble` numbers before
+/// it, along with its index.
+fn invalid_numbers<I>(nums: I, preamble: usize) -> impl Iterator<Item = (usize, u64)>
+where
+    I: IntoIterator<Item = u64>,
+{
+    let mut validator = Validator::new(preamble);
+    nums.into_iter()
+        .enumerate()
+        .filter(move |(_, num)| validator.push(*num) == Some(false))
+}
+
+fn parse<'a>(data: &'a str) -> impl Iterator<Item = u64> + 'a {
+    data.lines()
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(|s| s.parse::<u64>().unwrap())
+}
+
+/// Every range of at least two contiguous numbers that sums to the target.
+///
+/// The numbers must all be positive; this slides a window along them,
+/// growing it at the end while the sum is too small and shrinking it from
+/// the start while it is too large, so each number is added and removed at
+/// most once.
+struct SummingRanges<'a> {
+    nums: &'a [u64],
+    target: u64,
     start: usize,
     end: usize,
-    slice: &'a [T],
+    sum: u64,
 }
 
-impl<'a, T> ContiguousSlices<'a, T> {
-    fn new(slice: &'a [T]) -> Self {
+impl<'a> SummingRanges<'a> {
+    fn new(nums: &'a [u64], target: u64) -> Self {
         Self {
+            nums,
+            target,
             start: 0,
-            end: 1,
-            slice,
+            end: 0,
+            sum: 0,
         }
     }
 }
 
-impl<'a, T> Iterator for ContiguousSlices<'a, T> {
-    type Item = &'a [T];
+impl<'a> Iterator for SummingRanges<'a> {
+    type Item = Range<usize>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.end > self.slice.len() {
-            self.start += 1;
-            self.end = self.start + 1;
-        }
-
-        if self.end > self.slice.len() {
-            return None;
+        while self.end < self.nums.len() {
+            self.sum += self.nums[self.end];
+            self.end += 1;
+            while self.sum > self.target {
+                self.sum -= self.nums[self.start];
+                self.start += 1;
+            }
+            if self.sum == self.target && self.end - self.start >= 2 {
+                return Some(self.start..self.end);
+            }
         }
-
-        let slice = &self.slice[self.start..self.end];
-        self.end += 1;
-        Some(slice)
+        None
     }
 }
 
-trait SliceEx {
-    type Item;
-
-    fn all_pairs<'a>(&'a self) -> AllPairs<'a, Self::Item>;
+#[cfg(test)]
+mod tests {
+    use super::{invalid_numbers, parse, weakness, SummingRanges, Validator};
 
-    fn contiguous_slices<'a>(&'a self) -> ContiguousSlices<'a, Self::Item>;
-}
+    const TEST_NUMS: &str = include_str!("../data/day_09_test.txt");
 
-impl<T> SliceEx for [T] {
-    type Item = T;
+    #[test]
+    fn validator_finds_invalid_numbers() {
+        let nums = parse(TEST_NUMS);
+        assert_eq!(
+            invalid_numbers(nums, 5).collect::<Vec<_>>(),
+            vec![(14, 127)]
+        );
+    }
 
-    fn all_pairs<'a>(&'a self) -> AllPairs<'a, Self::Item> {
-        AllPairs::new(self)
+    #[test]
+    fn validator_finds_every_invalid_number() {
+        let nums = vec![1, 2, 3, 10, 4, 20, 24];
+        assert_eq!(
+            invalid_numbers(nums, 2).collect::<Vec<_>>(),
+            vec![(3, 10), (4, 4), (5, 20)]
+        );
     }
 
-    fn contiguous_slices<'a>(&'a self) -> ContiguousSlices<'a, Self::Item> {
-        ContiguousSlices::new(self)
+    #[test]
+    fn validator_requires_two_numbers() {
+        let mut validator = Validator::new(2);
+        assert_eq!(validator.push(3), None);
+        assert_eq!(validator.push(5), None);
+        assert_eq!(validator.push(6), Some(false));
+        assert_eq!(validator.push(11), Some(true));
+        assert_eq!(validator.push(8), Some(false));
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::SliceEx;
+    #[test]
+    fn summing_ranges_finds_weakness() {
+        let nums = parse(TEST_NUMS).collect::<Vec<_>>();
+        let (_, num) = invalid_numbers(nums.iter().copied(), 5).next().unwrap();
+        let ranges = SummingRanges::new(&nums, num).collect::<Vec<_>>();
+        assert_eq!(ranges, vec![2..6]);
+        assert_eq!(&nums[2..6], &[15, 25, 47, 40]);
+        assert_eq!(weakness(&nums[2..6]), 62);
+    }
 
     #[test]
-    fn all_pairs_returns_all_pairs() {
-        let list = vec![1, 2, 3, 4];
-        let pairs = list.all_pairs().collect::<Vec<_>>();
-        assert_eq!(
-            pairs,
-            vec![(&1, &2), (&1, &3), (&1, &4), (&2, &3), (&2, &4), (&3, &4)],
-        );
+    fn summing_ranges_skips_single_numbers() {
+        let nums = parse(TEST_NUMS).collect::<Vec<_>>();
+        let ranges = SummingRanges::new(&nums, 40).collect::<Vec<_>>();
+        assert_eq!(ranges, vec![2..4]);
     }
 
     #[test]
-    fn contiguous_slices_returns_all_slices() {
-        let list = vec![1, 2, 3, 4];
-        let slices = list.contiguous_slices().collect::<Vec<_>>();
-        assert_eq!(
-            slices,
-            vec![
-                &[1][..],
-                &[1, 2],
-                &[1, 2, 3],
-                &[1, 2, 3, 4],
-                &[2],
-                &[2, 3],
-                &[2, 3, 4],
-                &[3],
-                &[3, 4],
-                &[4],
-            ]
-        );
+    fn summing_ranges_finds_every_range() {
+        let nums = [1, 2, 3, 4, 5, 6];
+        let ranges = SummingRanges::new(&nums, 9).collect::<Vec<_>>();
+        assert_eq!(ranges, vec![1..4, 3..5]);
     }
 }
@@ -1,45 +1,370 @@
+use num::{BigUint, One, Zero};
+use std::collections::BTreeMap;
+
 pub fn star_1(data: String) {
-    let nums = parse(&data);
-    let (ones, threes) = nums
-        .iter()
-        .zip(nums.iter().skip(1))
-        .map(|(a, b)| b - a)
-        .fold((0, 0), |(ones, threes), x| match x {
-            1 => (ones + 1, threes),
-            3 => (ones, threes + 1),
-            _ => (ones, threes),
-        });
+    let chain = AdapterChain::new(parse(&data));
+    let gaps = chain.gap_histogram();
+    let ones = gaps.get(&1).copied().unwrap_or(0);
+    let threes = gaps.get(&3).copied().unwrap_or(0);
     let total = ones * threes;
     println!("{}", total);
 }
 
 pub fn star_2(data: String) {
-    let nums = parse(&data);
-    let mut choices_cache = vec![0u64; nums.len()];
-    choices_cache[nums.len() - 1] = 1;
-    for i in (0..(nums.len() - 1)).rev() {
-        let num = nums[i];
-        for j in (i + 1)..(i + 4) {
-            if let Some(x) = nums.get(j) {
-                if x - num <= 3 {
-                    choices_cache[i] += choices_cache[j];
-                }
+    let chain = AdapterChain::new(parse(&data));
+    let choices = chain.arrangement_count();
+    println!("{}", choices);
+}
+
+pub fn chain(data: String, args: &[String]) {
+    let mut chain = AdapterChain::new(parse(&data));
+    let mut device_offset = None;
+    let mut limit = 10;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().unwrap();
+        match arg.as_str() {
+            "gaps" => {
+                chain = chain.with_gaps(value().split(',').map(|g| g.parse().unwrap()).collect())
+            }
+            "outlet" => chain = chain.with_outlet(value().parse().unwrap()),
+            "device" => device_offset = Some(value().parse().unwrap()),
+            "limit" => limit = value().parse().unwrap(),
+            _ => {
+                println!("Unknown option {}.", arg);
+                return;
             }
         }
     }
-    let choices = choices_cache[0];
-    println!("{:?}", choices);
+    // Unless told otherwise, the device takes the biggest gap allowed.
+    let device_offset = device_offset.unwrap_or_else(|| chain.largest_gap());
+    chain = chain.with_device_offset(device_offset);
+
+    println!("gaps:");
+    for (gap, count) in chain.gap_histogram() {
+        println!("  {}: {}", gap, count);
+    }
+    println!("valid with every adapter: {}", chain.is_valid());
+    println!("arrangements: {}", chain.arrangement_count());
+    match chain.removable() {
+        Some(removable) => println!(
+            "removable adapters: {}",
+            removable
+                .iter()
+                .map(u64::to_string)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        None => println!("removable adapters: none, as the full chain isn't valid"),
+    }
+
+    if let Some(arrangements) = chain.arrangements(limit) {
+        for arrangement in arrangements {
+            let arrangement = arrangement.iter().map(u64::to_string).collect::<Vec<_>>();
+            println!("  {}", arrangement.join(" "));
+        }
+    }
 }
 
 fn parse(data: &str) -> Vec<u64> {
-    let mut nums = data
-        .lines()
+    data.lines()
         .map(str::trim)
         .filter(|s| !s.is_empty())
         .map(|s| s.parse::<u64>().unwrap())
-        .collect::<Vec<_>>();
-    nums.push(0);
-    nums.sort();
-    nums.push(nums[nums.len() - 1] + 3);
-    nums
+        .collect()
+}
+
+/// A bag of adapters between the charging outlet and the device.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct AdapterChain {
+    adapters: Vec<u64>,
+    gaps: Vec<u64>,
+    outlet: u64,
+    device_offset: u64,
+}
+
+impl AdapterChain {
+    /// Creates a chain from an outlet at 0 jolts, where each adapter can
+    /// take an input 1 to 3 jolts lower than its rating, and the device is
+    /// rated 3 jolts higher than the highest adapter.
+    fn new(mut adapters: Vec<u64>) -> Self {
+        adapters.sort_unstable();
+        Self {
+            adapters,
+            gaps: vec![1, 2, 3],
+            outlet: 0,
+            device_offset: 3,
+        }
+    }
+
+    /// Sets the gaps allowed between joltages. This doesn't move the
+    /// device, which stays at its own offset above the highest adapter.
+    fn with_gaps(mut self, mut gaps: Vec<u64>) -> Self {
+        gaps.sort_unstable();
+        gaps.dedup();
+        self.gaps = gaps;
+        self
+    }
+
+    /// Moves the outlet. Any adapter rated below the outlet can't be used
+    /// and is left out of the chain.
+    fn with_outlet(mut self, outlet: u64) -> Self {
+        self.outlet = outlet;
+        self
+    }
+
+    /// Sets how many jolts higher than the highest adapter the device is
+    /// rated.
+    fn with_device_offset(mut self, device_offset: u64) -> Self {
+        self.device_offset = device_offset;
+        self
+    }
+
+    fn largest_gap(&self) -> u64 {
+        self.gaps.last().copied().unwrap_or(0)
+    }
+
+    /// The adapters that can be plugged in above the outlet, in order.
+    fn usable(&self) -> &[u64] {
+        let start = self.adapters.partition_point(|a| *a < self.outlet);
+        &self.adapters[start..]
+    }
+
+    /// Every joltage in the full chain, from the outlet to the device.
+    fn joltages(&self) -> Vec<u64> {
+        let usable = self.usable();
+        let device = usable.last().unwrap_or(&self.outlet) + self.device_offset;
+        let mut joltages = Vec::with_capacity(usable.len() + 2);
+        joltages.push(self.outlet);
+        joltages.extend(usable);
+        joltages.push(device);
+        joltages
+    }
+
+    fn allows(&self, gap: u64) -> bool {
+        self.gaps.binary_search(&gap).is_ok()
+    }
+
+    /// Whether every adapter can be used at once.
+    fn is_valid(&self) -> bool {
+        self.joltages()
+            .windows(2)
+            .all(|w| self.allows(w[1].saturating_sub(w[0])))
+    }
+
+    /// How many times each gap appears when every adapter is used.
+    fn gap_histogram(&self) -> BTreeMap<u64, usize> {
+        let mut histogram = BTreeMap::new();
+        for w in self.joltages().windows(2) {
+            *histogram.entry(w[1].saturating_sub(w[0])).or_insert(0) += 1;
+        }
+        histogram
+    }
+
+    /// The number of ways to get from each joltage in the chain to the
+    /// device.
+    fn ways_to_device(&self, joltages: &[u64]) -> Vec<BigUint> {
+        let max_gap = self.largest_gap();
+        let mut ways = vec![BigUint::zero(); joltages.len()];
+        ways[joltages.len() - 1] = BigUint::one();
+        for i in (0..joltages.len() - 1).rev() {
+            let mut total = BigUint::zero();
+            for j in (i + 1)..joltages.len() {
+                let gap = joltages[j].saturating_sub(joltages[i]);
+                if gap > max_gap {
+                    break;
+                }
+                if self.allows(gap) {
+                    total += &ways[j];
+                }
+            }
+            ways[i] = total;
+        }
+        ways
+    }
+
+    fn arrangement_count(&self) -> BigUint {
+        self.ways_to_device(&self.joltages()).swap_remove(0)
+    }
+
+    /// The adapters that could each be left out on their own while still
+    /// using every other adapter, or `None` if the chain isn't valid with
+    /// every adapter to begin with.
+    fn removable(&self) -> Option<Vec<u64>> {
+        if !self.is_valid() {
+            return None;
+        }
+        let joltages = self.joltages();
+        let removable = joltages
+            .windows(3)
+            .filter(|w| self.allows(w[2].saturating_sub(w[0])))
+            .map(|w| w[1])
+            .collect();
+        Some(removable)
+    }
+
+    /// Lazily lists every arrangement, as the joltages from the outlet to
+    /// the device, as long as there are at most `limit` of them.
+    fn arrangements(&self, limit: usize) -> Option<Arrangements> {
+        let joltages = self.joltages();
+        let ways = self.ways_to_device(&joltages);
+        if ways[0] > BigUint::from(limit) {
+            return None;
+        }
+
+        let reaches_device = ways.iter().map(|w| !w.is_zero()).collect();
+        let stack = if ways[0].is_zero() {
+            Vec::new()
+        } else {
+            vec![(0, 1)]
+        };
+        Some(Arrangements {
+            chain: self.clone(),
+            joltages,
+            reaches_device,
+            stack,
+        })
+    }
+}
+
+/// A depth-first walk over the arrangements of a chain, skipping adapters
+/// that can't lead to the device.
+#[derive(Debug, Clone)]
+struct Arrangements {
+    chain: AdapterChain,
+    joltages: Vec<u64>,
+    reaches_device: Vec<bool>,
+    stack: Vec<(usize, usize)>,
+}
+
+impl Iterator for Arrangements {
+    type Item = Vec<u64>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let last = self.joltages.len() - 1;
+        let max_gap = self.chain.largest_gap();
+        while let Some((index, candidate)) = self.stack.last().copied() {
+            if index == last {
+                let arrangement = self.stack.iter().map(|(i, _)| self.joltages[*i]).collect();
+                self.stack.pop();
+                return Some(arrangement);
+            }
+
+            let from = self.joltages[index];
+            let next = (candidate..=last)
+                .take_while(|j| self.joltages[*j].saturating_sub(from) <= max_gap)
+                .find(|j| {
+                    self.reaches_device[*j]
+                        && self.chain.allows(self.joltages[*j].saturating_sub(from))
+                });
+            match next {
+                Some(j) => {
+                    self.stack.last_mut().unwrap().1 = j + 1;
+                    self.stack.push((j, j + 1));
+                }
+                None => {
+                    self.stack.pop();
+                }
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse, AdapterChain};
+    use num::BigUint;
+
+    const SMALL: &str = include_str!("../data/day_10_test_1.txt");
+    const LARGE: &str = include_str!("../data/day_10_test_2.txt");
+
+    #[test]
+    fn chain_counts_gaps() {
+        let histogram = AdapterChain::new(parse(SMALL)).gap_histogram();
+        assert_eq!(
+            histogram.into_iter().collect::<Vec<_>>(),
+            vec![(1, 7), (3, 5)]
+        );
+        let histogram = AdapterChain::new(parse(LARGE)).gap_histogram();
+        assert_eq!(
+            histogram.into_iter().collect::<Vec<_>>(),
+            vec![(1, 22), (3, 10)]
+        );
+    }
+
+    #[test]
+    fn chain_counts_arrangements() {
+        assert_eq!(
+            AdapterChain::new(parse(SMALL)).arrangement_count(),
+            BigUint::from(8u32)
+        );
+        assert_eq!(
+            AdapterChain::new(parse(LARGE)).arrangement_count(),
+            BigUint::from(19208u32)
+        );
+    }
+
+    #[test]
+    fn chain_counts_beyond_u64() {
+        let chain = AdapterChain::new((1..=200).collect());
+        let count = chain.arrangement_count();
+        assert!(count > BigUint::from(u64::MAX));
+        assert_eq!(
+            count.to_string(),
+            "52622583840983769603765180599790256716084480555530641"
+        );
+    }
+
+    #[test]
+    fn chain_lists_arrangements() {
+        let chain = AdapterChain::new(parse(SMALL));
+        let arrangements = chain.arrangements(10).unwrap().collect::<Vec<_>>();
+        assert_eq!(arrangements.len(), 8);
+        assert_eq!(
+            arrangements[0],
+            vec![0, 1, 4, 5, 6, 7, 10, 11, 12, 15, 16, 19, 22]
+        );
+        assert_eq!(arrangements[7], vec![0, 1, 4, 7, 10, 12, 15, 16, 19, 22]);
+        assert!(chain.arrangements(7).is_none());
+    }
+
+    #[test]
+    fn chain_finds_removable_adapters() {
+        let chain = AdapterChain::new(parse(SMALL));
+        assert_eq!(chain.removable(), Some(vec![5, 6, 11]));
+        assert_eq!(chain.with_gaps(vec![1, 2]).removable(), None);
+    }
+
+    #[test]
+    fn chain_uses_configured_gaps() {
+        let chain = AdapterChain::new(vec![1, 2, 3, 6]).with_gaps(vec![1, 3]);
+        assert!(chain.is_valid());
+        let arrangements = chain.arrangements(10).unwrap().collect::<Vec<_>>();
+        assert_eq!(arrangements, vec![vec![0, 1, 2, 3, 6, 9], vec![0, 3, 6, 9]]);
+        assert_eq!(chain.arrangement_count(), BigUint::from(2u32));
+        assert!(!AdapterChain::new(vec![2, 4]).with_gaps(vec![1]).is_valid());
+    }
+
+    #[test]
+    fn chain_uses_configured_outlet_and_device() {
+        let chain = AdapterChain::new(vec![11, 12, 14])
+            .with_gaps(vec![1, 2])
+            .with_outlet(10)
+            .with_device_offset(2);
+        assert_eq!(chain.joltages(), vec![10, 11, 12, 14, 16]);
+        assert!(chain.is_valid());
+        assert_eq!(chain.arrangement_count(), BigUint::from(2u32));
+        assert_eq!(chain.removable(), Some(vec![11]));
+
+        let chain = AdapterChain::new(vec![1, 4, 5]).with_outlet(3);
+        assert_eq!(chain.joltages(), vec![3, 4, 5, 8]);
+        assert!(chain.is_valid());
+        assert_eq!(chain.arrangement_count(), BigUint::from(2u32));
+        assert_eq!(chain.removable(), Some(vec![4]));
+
+        let chain = AdapterChain::new(vec![1, 2]).with_outlet(5);
+        assert_eq!(chain.joltages(), vec![5, 8]);
+        assert_eq!(chain.arrangement_count(), BigUint::from(1u32));
+    }
 }
@@ -1,197 +1,103 @@
-use std::convert::TryFrom;
+use crate::automaton::{Automaton, Dense, Grid, Precomputed, Reach, Rule};
+use std::{convert::TryFrom, fs, path::Path, thread, time::Duration};
+
+use self::render::{ansi_frame, ppm_image};
+
+mod render;
 
 pub fn star_1(data: String) {
-    let mut old = parse(&data);
-    let mut new = old.clone();
-    loop {
-        step_1(&old, &mut new);
-        if old == new {
-            break;
-        }
-        std::mem::swap(&mut old, &mut new);
-    }
-    let occupied = new
-        .iter()
-        .map(|r| {
-            r.iter()
-                .map(|c| if c.is_occupied() { 1 } else { 0 })
-                .sum::<u32>()
-        })
-        .sum::<u32>();
-    println!("{}", occupied);
+    let mut seats = seating(&parse(&data), Reach::Adjacent, 4);
+    seats.run_until_stable();
+    println!("{}", seats.population());
 }
 
 pub fn star_2(data: String) {
-    let mut old = parse(&data);
-    let mut new = old.clone();
-    loop {
-        step_2(&old, &mut new);
-        if old == new {
-            break;
-        }
-        std::mem::swap(&mut old, &mut new);
-    }
-    let occupied = new
-        .iter()
-        .map(|r| {
-            r.iter()
-                .map(|c| if c.is_occupied() { 1 } else { 0 })
-                .sum::<u32>()
-        })
-        .sum::<u32>();
-    println!("{}", occupied);
+    let mut seats = seating(&parse(&data), Reach::LineOfSight, 5);
+    seats.run_until_stable();
+    println!("{}", seats.population());
 }
 
-fn step_1(old: &Vec<Vec<Cell>>, new: &mut Vec<Vec<Cell>>) {
-    for row in 0..old.len() {
-        for col in 0..old[row].len() {
-            if old[row][col] == Cell::Floor {
-                new[row][col] = Cell::Floor;
-                continue;
-            }
-
-            let mut neighbor_sum = 0;
-
-            if row > 0 {
-                if col > 0 {
-                    neighbor_sum += if old[row - 1][col - 1].is_occupied() {
-                        1
-                    } else {
-                        0
-                    };
-                }
-                neighbor_sum += if old[row - 1][col].is_occupied() {
-                    1
-                } else {
-                    0
-                };
-                if col < old[row].len() - 1 {
-                    neighbor_sum += if old[row - 1][col + 1].is_occupied() {
-                        1
-                    } else {
-                        0
-                    };
-                }
-            }
-            if col > 0 {
-                neighbor_sum += if old[row][col - 1].is_occupied() {
-                    1
-                } else {
-                    0
-                };
-            }
-            if col < old[row].len() - 1 {
-                neighbor_sum += if old[row][col + 1].is_occupied() {
-                    1
-                } else {
-                    0
-                };
-            }
-            if row < old.len() - 1 {
-                if col > 0 {
-                    neighbor_sum += if old[row + 1][col - 1].is_occupied() {
-                        1
-                    } else {
-                        0
-                    };
-                }
-                neighbor_sum += if old[row + 1][col].is_occupied() {
-                    1
-                } else {
-                    0
-                };
-                if col < old[row].len() - 1 {
-                    neighbor_sum += if old[row + 1][col + 1].is_occupied() {
-                        1
-                    } else {
-                        0
-                    };
-                }
-            }
-
-            if old[row][col] == Cell::Empty && neighbor_sum == 0 {
-                new[row][col] = Cell::Occupied;
-            } else if old[row][col] == Cell::Occupied && neighbor_sum >= 4 {
-                new[row][col] = Cell::Empty;
-            } else {
-                new[row][col] = old[row][col];
+/// Shows each generation until the seating settles, either as terminal frames
+/// or as a numbered sequence of PPM images in a directory.
+pub fn animate(data: String, args: &[String]) {
+    let mut cells = parse(&data);
+    let mut reach = Reach::Adjacent;
+    let mut delay = 200;
+    let mut images = None;
+    let mut scale = 4;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "sight" => reach = Reach::LineOfSight,
+            "delay" => delay = args.next().unwrap().parse().unwrap(),
+            "ppm" => images = Some(Path::new(args.next().unwrap())),
+            "scale" => scale = args.next().unwrap().parse().unwrap(),
+            _ => {
+                println!("Unknown option {}.", arg);
+                return;
             }
         }
     }
-}
+    let tolerance = match reach {
+        Reach::Adjacent => 4,
+        Reach::LineOfSight => 5,
+    };
 
-fn step_2(old: &Vec<Vec<Cell>>, new: &mut Vec<Vec<Cell>>) {
-    for row in 0..old.len() {
-        for col in 0..old[row].len() {
-            if old[row][col] == Cell::Floor {
-                new[row][col] = Cell::Floor;
-                continue;
+    if let Some(dir) = images {
+        fs::create_dir_all(dir).unwrap();
+    }
+    let mut seats = seating(&cells, reach, tolerance);
+    let mut generation = 0;
+    loop {
+        let status = format!("generation {}: {} occupied", generation, seats.population());
+        match images {
+            Some(dir) => {
+                let path = dir.join(format!("gen_{:04}.ppm", generation));
+                fs::write(path, ppm_image(&cells, scale)).unwrap();
+                println!("{}", status);
             }
-
-            let ul = raycast(&old, row, col, Direction::Prev, Direction::Prev);
-            let u = raycast(&old, row, col, Direction::Prev, Direction::Same);
-            let ur = raycast(&old, row, col, Direction::Prev, Direction::Next);
-            let l = raycast(&old, row, col, Direction::Same, Direction::Prev);
-            let r = raycast(&old, row, col, Direction::Same, Direction::Next);
-            let dl = raycast(&old, row, col, Direction::Next, Direction::Prev);
-            let d = raycast(&old, row, col, Direction::Next, Direction::Same);
-            let dr = raycast(&old, row, col, Direction::Next, Direction::Next);
-            let neighbor_sum = [ul, u, ur, l, r, dl, d, dr]
-                .iter()
-                .map(|o| if *o { 1 } else { 0 })
-                .sum::<u32>();
-
-            if old[row][col] == Cell::Empty && neighbor_sum == 0 {
-                new[row][col] = Cell::Occupied;
-            } else if old[row][col] == Cell::Occupied && neighbor_sum >= 5 {
-                new[row][col] = Cell::Empty;
-            } else {
-                new[row][col] = old[row][col];
+            None => {
+                // The next frame clears the screen, so the status has to be
+                // shown before waiting.
+                print!("{}", ansi_frame(&cells));
+                println!("{}", status);
+                thread::sleep(Duration::from_millis(delay));
             }
         }
+        if !seats.step() {
+            break;
+        }
+        generation += 1;
+        update(&mut cells, &seats);
     }
 }
 
-fn raycast(
-    cells: &Vec<Vec<Cell>>,
-    row: usize,
-    col: usize,
-    row_dir: Direction,
-    col_dir: Direction,
-) -> bool {
-    let mut r = row;
-    let mut c = col;
-    loop {
-        if (r == 0 && row_dir == Direction::Prev)
-            || (r == cells.len() - 1 && row_dir == Direction::Next)
-            || (c == 0 && col_dir == Direction::Prev)
-            || (c == cells[r].len() - 1 && col_dir == Direction::Next)
-        {
-            return false;
-        }
-        r = match row_dir {
-            Direction::Prev => r - 1,
-            Direction::Same => r,
-            Direction::Next => r + 1,
-        };
-        c = match col_dir {
-            Direction::Prev => c - 1,
-            Direction::Same => c,
-            Direction::Next => c + 1,
-        };
-        match cells[r][c] {
-            Cell::Floor => {}
-            Cell::Empty => return false,
-            Cell::Occupied => return true,
+/// Copies the state of the seating system back into the layout.
+fn update(cells: &mut [Vec<Cell>], seats: &Dense<Precomputed>) {
+    for (i, cell) in cells.iter_mut().flatten().enumerate() {
+        if *cell != Cell::Floor {
+            *cell = if seats.is_alive(i) {
+                Cell::Occupied
+            } else {
+                Cell::Empty
+            };
         }
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-enum Direction {
-    Prev,
-    Same,
-    Next,
+/// Sets up the seating system, where people sit in empty seats with no
+/// occupied neighbours and leave once `tolerance` neighbours are occupied.
+///
+/// Which seats each seat can see is worked out once, so each generation only
+/// has to count through a table.
+fn seating(cells: &[Vec<Cell>], reach: Reach, tolerance: usize) -> Dense<Precomputed> {
+    let width = cells.first().map_or(0, Vec::len);
+    let cells = cells.iter().flatten().copied().collect::<Vec<_>>();
+    let present = cells.iter().map(|c| *c != Cell::Floor).collect();
+    let occupied = (0..cells.len()).filter(|i| cells[*i] == Cell::Occupied);
+    let rule = Rule::new(vec![0], 0..tolerance);
+    let visible = Precomputed::new(&Grid::new(width, present, reach));
+    Dense::new(visible, rule, occupied)
 }
 
 fn parse(data: &str) -> Vec<Vec<Cell>> {
@@ -216,12 +122,6 @@ enum Cell {
     Occupied,
 }
 
-impl Cell {
-    fn is_occupied(&self) -> bool {
-        *self == Cell::Occupied
-    }
-}
-
 impl TryFrom<char> for Cell {
     type Error = ();
 
@@ -234,3 +134,63 @@ impl TryFrom<char> for Cell {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        parse,
+        render::{ansi_frame, ppm_image},
+        seating, update,
+    };
+    use crate::automaton::{Automaton, Reach};
+
+    const TEST_SEATS: &str = include_str!("../data/day_11_test.txt");
+
+    #[test]
+    fn adjacent_seating_settles() {
+        let mut seats = seating(&parse(TEST_SEATS), Reach::Adjacent, 4);
+        assert_eq!(seats.run_until_stable(), 5);
+        assert_eq!(seats.population(), 37);
+    }
+
+    #[test]
+    fn line_of_sight_seating_settles() {
+        let mut seats = seating(&parse(TEST_SEATS), Reach::LineOfSight, 5);
+        assert_eq!(seats.run_until_stable(), 6);
+        assert_eq!(seats.population(), 26);
+    }
+
+    #[test]
+    fn update_copies_generations_back() {
+        let mut cells = parse(TEST_SEATS);
+        let mut seats = seating(&cells, Reach::Adjacent, 4);
+        seats.step();
+        update(&mut cells, &seats);
+        assert_eq!(
+            cells,
+            parse(
+                include_str!("../data/day_11_test.txt")
+                    .replace('L', "#")
+                    .as_str()
+            )
+        );
+    }
+
+    #[test]
+    fn renders_frames() {
+        let cells = parse("L.#");
+        assert_eq!(
+            ansi_frame(&cells),
+            "\x1b[2J\x1b[H\x1b[32mL\x1b[0m.\x1b[1;31m#\x1b[0m\n"
+        );
+
+        let image = ppm_image(&cells, 2);
+        let header = b"P6\n6 2\n255\n";
+        assert_eq!(&image[..header.len()], header);
+        assert_eq!(image.len(), header.len() + 6 * 2 * 3);
+        assert_eq!(
+            &image[header.len()..header.len() + 6],
+            &[48, 160, 64, 48, 160, 64]
+        );
+    }
+}
@@ -0,0 +1,56 @@
+use super::Cell;
+use std::fmt::{self, Write};
+
+const CLEAR: &str = "\x1b[2J\x1b[H";
+const RESET: &str = "\x1b[0m";
+
+/// Draws the seating area with empty seats in green and occupied seats in
+/// red, after clearing the terminal.
+pub fn ansi_frame(cells: &[Vec<Cell>]) -> String {
+    let mut frame = String::from(CLEAR);
+    write_ansi(&mut frame, cells).unwrap();
+    frame
+}
+
+fn write_ansi<W: Write>(out: &mut W, cells: &[Vec<Cell>]) -> fmt::Result {
+    for row in cells {
+        for cell in row {
+            match cell {
+                Cell::Floor => write!(out, ".")?,
+                Cell::Empty => write!(out, "\x1b[32mL{}", RESET)?,
+                Cell::Occupied => write!(out, "\x1b[1;31m#{}", RESET)?,
+            }
+        }
+        writeln!(out)?;
+    }
+    Ok(())
+}
+
+/// Draws the seating area as a binary PPM image, with each cell a
+/// `scale`-pixel square.
+pub fn ppm_image(cells: &[Vec<Cell>], scale: usize) -> Vec<u8> {
+    let height = cells.len();
+    let width = cells.first().map_or(0, Vec::len);
+    let mut image = format!("P6\n{} {}\n255\n", width * scale, height * scale).into_bytes();
+    for row in cells {
+        let line = row
+            .iter()
+            .flat_map(|cell| {
+                let rgb: [u8; 3] = match cell {
+                    Cell::Floor => [32, 32, 32],
+                    Cell::Empty => [48, 160, 64],
+                    Cell::Occupied => [208, 48, 48],
+                };
+                rgb.iter()
+                    .copied()
+                    .cycle()
+                    .take(3 * scale)
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        for _ in 0..scale {
+            image.extend_from_slice(&line);
+        }
+    }
+    image
+}
@@ -1,27 +1,122 @@
 use crate::utils::u32_;
 use nom::{
     branch::alt,
-    character::complete::{anychar, digit1},
-    combinator::{map, map_opt, map_res},
+    character::complete::anychar,
+    combinator::{map, map_opt},
     IResult,
 };
+use std::{
+    fmt, fs,
+    ops::{Add, Mul},
+};
+
+use self::{
+    planner::{shortest_plan, Allowed, Model},
+    svg::to_svg,
+};
+
+mod planner;
+mod svg;
 
 pub fn star_1(data: String) {
-    let actions = parse(&data);
-    let mut ship = BasicShip::default();
-    for action in actions {
-        ship.take_action(action);
-    }
+    let ship = sail(BasicShip::default(), &parse(&data));
     println!("{}", ship.manhattan_distance_from_origin());
 }
 
 pub fn star_2(data: String) {
+    let ship = sail(ShipWithWaypoint::default(), &parse(&data));
+    println!("{}", ship.manhattan_distance_from_origin());
+}
+
+pub fn path(data: String, _args: &[String]) {
+    let actions = parse(&data);
+    let basic = sail(BasicShip::default(), &actions);
+    let waypoint = sail(ShipWithWaypoint::default(), &actions);
+    let ships: [(&str, &dyn Ship); 2] = [("basic", &basic), ("waypoint", &waypoint)];
+    for (name, ship) in &ships {
+        let (min, max) = ship.bounding_box();
+        println!("{}:", name);
+        println!("  end: ({}, {})", ship.position().x, ship.position().y);
+        println!(
+            "  bounding box: ({}, {}) to ({}, {})",
+            min.x, min.y, max.x, max.y
+        );
+        println!("  furthest: {}", ship.max_manhattan_distance());
+    }
+}
+
+pub fn svg(data: String, args: &[String]) {
     let actions = parse(&data);
-    let mut ship = ShipWithWaypoint::default();
+    let basic = sail(BasicShip::default(), &actions);
+    let waypoint = sail(ShipWithWaypoint::default(), &actions);
+    let show_waypoints = args[1..].iter().any(|a| a == "waypoints");
+    fs::write(&args[0], to_svg(&basic, &waypoint, show_waypoints)).unwrap();
+}
+
+/// Plans a shortest route to a position, by default the one the input
+/// ends up at, printing it as instructions.
+pub fn plan(data: String, args: &[String]) {
+    let model = match args.first().map(String::as_str) {
+        Some("basic") => Model::Basic,
+        Some("waypoint") => Model::Waypoint,
+        _ => {
+            println!("Expected basic or waypoint.");
+            return;
+        }
+    };
+    let mut target = None;
+    let mut allowed = Allowed::ALL;
+    let mut args = args[1..].iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "to" => {
+                let x = args.next().unwrap().parse().unwrap();
+                let y = args.next().unwrap().parse().unwrap();
+                target = Some((x, y));
+            }
+            "only" => allowed = Allowed::from_letters(args.next().unwrap()),
+            _ => {
+                println!("Unknown option {}.", arg);
+                return;
+            }
+        }
+    }
+    let target = match target {
+        Some(target) => target,
+        None => {
+            let actions = parse(&data);
+            let end = match model {
+                Model::Basic => sail(BasicShip::default(), &actions).position(),
+                Model::Waypoint => sail(ShipWithWaypoint::default(), &actions).position(),
+            };
+            // Plans only ever make quarter turns, so they can't reach a
+            // position that isn't on the grid.
+            if end.x.fract() != 0.0 || end.y.fract() != 0.0 {
+                println!(
+                    "The input ends at ({}, {}), which isn't a whole position.",
+                    end.x, end.y
+                );
+                return;
+            }
+            (end.x as i64, end.y as i64)
+        }
+    };
+
+    match shortest_plan(model, target, allowed) {
+        Some(actions) => {
+            for action in actions {
+                println!("{}", action);
+            }
+        }
+        None => println!("No plan reaches ({}, {}).", target.0, target.1),
+    }
+}
+
+fn sail<S: Ship>(mut ship: S, actions: &[Action]) -> S {
     for action in actions {
-        ship.take_action(action);
+        ship.take_action(*action);
     }
-    println!("{}", ship.manhattan_distance_from_origin());
+    ship
 }
 
 fn parse(data: &str) -> Vec<Action> {
@@ -34,22 +129,111 @@ fn parse(data: &str) -> Vec<Action> {
 
 trait Ship {
     fn take_action(&mut self, action: Action);
-    fn manhattan_distance_from_origin(&self) -> u32;
+
+    /// Every position the ship has been at, starting from the origin.
+    fn path(&self) -> &[Vector];
+
+    fn position(&self) -> Vector {
+        *self.path().last().unwrap()
+    }
+
+    fn manhattan_distance_from_origin(&self) -> f64 {
+        self.position().manhattan()
+    }
+
+    /// The furthest the ship got from the origin at any point. The ship
+    /// moves in straight lines, so this is always at a corner of its path.
+    fn max_manhattan_distance(&self) -> f64 {
+        self.path()
+            .iter()
+            .map(|p| p.manhattan())
+            .fold(0.0, f64::max)
+    }
+
+    /// The smallest and largest coordinates the ship reached.
+    fn bounding_box(&self) -> (Vector, Vector) {
+        let mut min = self.path()[0];
+        let mut max = min;
+        for p in self.path() {
+            min = Vector::new(min.x.min(p.x), min.y.min(p.y));
+            max = Vector::new(max.x.max(p.x), max.y.max(p.y));
+        }
+        (min, max)
+    }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// A position or offset, with x increasing to the east and y to the south.
+///
+/// Rotating by a multiple of 90 degrees only swaps and negates the
+/// coordinates, so it's exact. Any other angle goes through floating point
+/// trigonometry, which is why ships keep track of their net rotation rather
+/// than rotating their heading one turn at a time.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+struct Vector {
+    x: f64,
+    y: f64,
+}
+
+impl Vector {
+    fn new(x: f64, y: f64) -> Self {
+        Self { x, y }
+    }
+
+    fn manhattan(self) -> f64 {
+        self.x.abs() + self.y.abs()
+    }
+
+    /// Rotates clockwise by a number of degrees between 0 and 359.
+    fn rotated(self, degrees: u32) -> Self {
+        let Self { x, y } = self;
+        match degrees {
+            0 => self,
+            90 => Self::new(-y, x),
+            180 => Self::new(-x, -y),
+            270 => Self::new(y, -x),
+            degrees => {
+                let (sin, cos) = f64::from(degrees).to_radians().sin_cos();
+                Self::new(x * cos - y * sin, x * sin + y * cos)
+            }
+        }
+    }
+}
+
+impl Add for Vector {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self::new(self.x + other.x, self.y + other.y)
+    }
+}
+
+impl Mul<u32> for Vector {
+    type Output = Self;
+
+    fn mul(self, amt: u32) -> Self {
+        Self::new(self.x * f64::from(amt), self.y * f64::from(amt))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 struct BasicShip {
-    facing: Direction,
-    horiz_pos: i32,
-    vert_pos: i32,
+    /// How far the ship has turned clockwise from east, between 0 and 359
+    /// degrees.
+    heading: u32,
+    path: Vec<Vector>,
+}
+
+impl BasicShip {
+    fn facing(&self) -> Vector {
+        Direction::East.unit().rotated(self.heading)
+    }
 }
 
 impl Default for BasicShip {
     fn default() -> Self {
         Self {
-            facing: Direction::East,
-            horiz_pos: 0,
-            vert_pos: 0,
+            heading: 0,
+            path: vec![Vector::default()],
         }
     }
 }
@@ -58,37 +242,52 @@ impl Ship for BasicShip {
     fn take_action(&mut self, action: Action) {
         match action {
             Action::Rotate(rot) => {
-                self.facing = self.facing.with_rotation(rot);
+                self.heading = (self.heading + rot.clockwise_degrees()) % 360;
             }
             Action::Move(dir, amt) => {
-                let dir = dir.unwrap_or(self.facing);
-                let (horiz, vert) = (dir.horizontal(), dir.vertical());
-                self.horiz_pos += horiz * (amt as i32);
-                self.vert_pos += vert * (amt as i32);
+                let dir = dir.map_or(self.facing(), Direction::unit);
+                self.path.push(self.position() + dir * amt);
             }
         }
     }
 
-    fn manhattan_distance_from_origin(&self) -> u32 {
-        (self.horiz_pos.abs() + self.vert_pos.abs()) as u32
+    fn path(&self) -> &[Vector] {
+        &self.path
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq)]
 struct ShipWithWaypoint {
-    horiz_pos: i32,
-    vert_pos: i32,
-    waypoint_horiz_offset: i32,
-    waypoint_vert_offset: i32,
+    /// The waypoint's offset before the rotation is applied.
+    offset: Vector,
+    /// How far the waypoint has turned clockwise around the ship, between 0
+    /// and 359 degrees.
+    heading: u32,
+    path: Vec<Vector>,
+    waypoints: Vec<(Vector, Vector)>,
+}
+
+impl ShipWithWaypoint {
+    /// The waypoint's offset from the ship.
+    fn waypoint(&self) -> Vector {
+        self.offset.rotated(self.heading)
+    }
+
+    /// The ship's position and the waypoint's offset from it, at the start
+    /// and after every action.
+    fn waypoints(&self) -> &[(Vector, Vector)] {
+        &self.waypoints
+    }
 }
 
 impl Default for ShipWithWaypoint {
     fn default() -> Self {
+        let waypoint = Vector::new(10.0, -1.0);
         Self {
-            horiz_pos: 0,
-            vert_pos: 0,
-            waypoint_horiz_offset: 10,
-            waypoint_vert_offset: -1,
+            offset: waypoint,
+            heading: 0,
+            path: vec![Vector::default()],
+            waypoints: vec![(Vector::default(), waypoint)],
         }
     }
 }
@@ -97,28 +296,23 @@ impl Ship for ShipWithWaypoint {
     fn take_action(&mut self, action: Action) {
         match action {
             Action::Rotate(rot) => {
-                let (horiz, vert) = match rot.clockwise_angle() {
-                    Angle::Zero => (self.waypoint_horiz_offset, self.waypoint_vert_offset),
-                    Angle::Ninety => (-self.waypoint_vert_offset, self.waypoint_horiz_offset),
-                    Angle::OneEighty => (-self.waypoint_horiz_offset, -self.waypoint_vert_offset),
-                    Angle::TwoSeventy => (self.waypoint_vert_offset, -self.waypoint_horiz_offset),
-                };
-                self.waypoint_horiz_offset = horiz;
-                self.waypoint_vert_offset = vert;
+                self.heading = (self.heading + rot.clockwise_degrees()) % 360;
             }
             Action::Move(Some(dir), amt) => {
-                self.waypoint_horiz_offset += dir.horizontal() * amt as i32;
-                self.waypoint_vert_offset += dir.vertical() * amt as i32;
+                // Undo the rotation on the move instead of redoing it on
+                // the offset.
+                let unrotated = dir.unit().rotated((360 - self.heading) % 360);
+                self.offset = self.offset + unrotated * amt;
             }
             Action::Move(None, amt) => {
-                self.horiz_pos += self.waypoint_horiz_offset * amt as i32;
-                self.vert_pos += self.waypoint_vert_offset * amt as i32;
+                self.path.push(self.position() + self.waypoint() * amt);
             }
         }
+        self.waypoints.push((self.position(), self.waypoint()));
     }
 
-    fn manhattan_distance_from_origin(&self) -> u32 {
-        (self.horiz_pos.abs() + self.vert_pos.abs()) as u32
+    fn path(&self) -> &[Vector] {
+        &self.path
     }
 }
 
@@ -128,6 +322,30 @@ enum Action {
     Move(Option<Direction>, u32),
 }
 
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Action::Rotate(rot) => {
+                let letter = match rot.direction {
+                    RotationDirection::Clockwise => 'R',
+                    RotationDirection::CounterClockwise => 'L',
+                };
+                write!(f, "{}{}", letter, rot.degrees)
+            }
+            Action::Move(dir, amt) => {
+                let letter = match dir {
+                    Some(Direction::North) => 'N',
+                    Some(Direction::South) => 'S',
+                    Some(Direction::East) => 'E',
+                    Some(Direction::West) => 'W',
+                    None => 'F',
+                };
+                write!(f, "{}{}", letter, amt)
+            }
+        }
+    }
+}
+
 fn action(input: &str) -> IResult<&str, Action> {
     alt((
         map(rotation, Action::Rotate),
@@ -141,14 +359,8 @@ fn rotation(input: &str) -> IResult<&str, Rotation> {
         'R' => Some(RotationDirection::Clockwise),
         _ => None,
     })(input)?;
-    let (input, angle) = map_opt(u32_, |d| match d {
-        0 => Some(Angle::Zero),
-        90 => Some(Angle::Ninety),
-        180 => Some(Angle::OneEighty),
-        270 => Some(Angle::TwoSeventy),
-        _ => None,
-    })(input)?;
-    Ok((input, Rotation { direction, angle }))
+    let (input, degrees) = u32_(input)?;
+    Ok((input, Rotation { direction, degrees }))
 }
 
 fn movement(input: &str) -> IResult<&str, (Option<Direction>, u32)> {
@@ -164,33 +376,24 @@ fn movement(input: &str) -> IResult<&str, (Option<Direction>, u32)> {
     Ok((input, (dir, amt)))
 }
 
+/// A turn through any whole number of degrees.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 struct Rotation {
     direction: RotationDirection,
-    angle: Angle,
+    degrees: u32,
 }
 
 impl Rotation {
-    fn clockwise_angle(self) -> Angle {
-        if self.direction == RotationDirection::Clockwise {
-            return self.angle;
-        }
-        match self.angle {
-            Angle::Ninety => Angle::TwoSeventy,
-            Angle::TwoSeventy => Angle::Ninety,
-            a => a,
+    /// The same turn made clockwise, between 0 and 359 degrees.
+    fn clockwise_degrees(self) -> u32 {
+        let degrees = self.degrees % 360;
+        match self.direction {
+            RotationDirection::Clockwise => degrees,
+            RotationDirection::CounterClockwise => (360 - degrees) % 360,
         }
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-enum Angle {
-    Zero,
-    Ninety,
-    OneEighty,
-    TwoSeventy,
-}
-
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum RotationDirection {
     Clockwise,
@@ -206,15 +409,8 @@ enum Direction {
 }
 
 impl Direction {
-    fn with_rotation(self, rotation: Rotation) -> Self {
-        use Angle::*;
-        use Direction::*;
-        match (self, rotation.clockwise_angle()) {
-            (North, Zero) | (West, Ninety) | (South, OneEighty) | (East, TwoSeventy) => North,
-            (East, Zero) | (North, Ninety) | (West, OneEighty) | (South, TwoSeventy) => East,
-            (South, Zero) | (East, Ninety) | (North, OneEighty) | (West, TwoSeventy) => South,
-            (West, Zero) | (South, Ninety) | (East, OneEighty) | (North, TwoSeventy) => West,
-        }
+    fn unit(self) -> Vector {
+        Vector::new(f64::from(self.horizontal()), f64::from(self.vertical()))
     }
 
     fn horizontal(self) -> i32 {
@@ -233,3 +429,220 @@ impl Direction {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        action, parse,
+        planner::{shortest_plan, Allowed, Model},
+        sail,
+        svg::to_svg,
+        Action, BasicShip, Ship, ShipWithWaypoint, Vector,
+    };
+    use std::collections::HashMap;
+
+    const TEST_ACTIONS: &str = include_str!("../data/day_12_test.txt");
+
+    fn sailed<S: Ship + Default>(actions: &str) -> S {
+        sail(S::default(), &parse(actions))
+    }
+
+    fn assert_near(a: Vector, b: Vector) {
+        assert!(
+            (a.x - b.x).abs() < 1e-9 && (a.y - b.y).abs() < 1e-9,
+            "{:?} != {:?}",
+            a,
+            b
+        );
+    }
+
+    #[test]
+    fn basic_ship_records_path() {
+        let ship = sailed::<BasicShip>(TEST_ACTIONS);
+        assert_eq!(ship.manhattan_distance_from_origin(), 25.0);
+        assert_eq!(
+            ship.path(),
+            &[
+                Vector::new(0.0, 0.0),
+                Vector::new(10.0, 0.0),
+                Vector::new(10.0, -3.0),
+                Vector::new(17.0, -3.0),
+                Vector::new(17.0, 8.0),
+            ]
+        );
+        assert_eq!(
+            ship.bounding_box(),
+            (Vector::new(0.0, -3.0), Vector::new(17.0, 8.0))
+        );
+    }
+
+    #[test]
+    fn waypoint_ship_records_path() {
+        let ship = sailed::<ShipWithWaypoint>(TEST_ACTIONS);
+        assert_eq!(ship.position(), Vector::new(214.0, 72.0));
+        assert_eq!(ship.manhattan_distance_from_origin(), 286.0);
+        assert_eq!(ship.max_manhattan_distance(), 286.0);
+        assert_eq!(ship.path().len(), 4);
+    }
+
+    #[test]
+    fn ships_turn_through_any_angle() {
+        assert!(action("R45").is_ok());
+
+        let ship = sailed::<BasicShip>("R45\nF10\nL135\nF2");
+        let diagonal = 10.0 / 2f64.sqrt();
+        assert_near(ship.path()[1], Vector::new(diagonal, diagonal));
+        assert_near(ship.position(), Vector::new(diagonal, diagonal - 2.0));
+
+        let ship = sailed::<ShipWithWaypoint>("L450\nF1\nR30\nF1");
+        assert_eq!(ship.path()[1], Vector::new(-1.0, -10.0));
+        let (sin, cos) = 30f64.to_radians().sin_cos();
+        let turned = Vector::new(10.0 * sin - cos, -sin - 10.0 * cos);
+        assert_near(ship.position(), Vector::new(-1.0, -10.0) + turned);
+    }
+
+    #[test]
+    fn split_quarter_turns_stay_exact() {
+        let split = "R45\nR45\nF10\nN3\nL30\nL60\nF10\nR135\nE7\nL135\nF3";
+        let whole = split
+            .replace("R45\nR45", "R90")
+            .replace("L30\nL60", "L90")
+            .replace("R135\nE7\nL135", "E7");
+        let split_ship = sailed::<BasicShip>(split);
+        let whole_ship = sailed::<BasicShip>(&whole);
+        assert_eq!(split_ship.position(), whole_ship.position());
+        assert_eq!(split_ship.facing(), whole_ship.facing());
+
+        let basic = sailed::<BasicShip>("R45\nR45\nF10\nL45\nL45\nF10");
+        assert_eq!(basic.position(), Vector::new(10.0, 10.0));
+        assert_eq!(basic.manhattan_distance_from_origin(), 20.0);
+
+        let split_ship = sailed::<ShipWithWaypoint>("R45\nR45\nF10\nN3\nL30\nL60\nF10");
+        let whole_ship = sailed::<ShipWithWaypoint>("R90\nF10\nN3\nL90\nF10");
+        assert_eq!(split_ship.position(), whole_ship.position());
+        assert_eq!(split_ship.waypoint(), whole_ship.waypoint());
+    }
+
+    #[test]
+    fn svg_overlays_both_routes() {
+        let basic = sailed::<BasicShip>(TEST_ACTIONS);
+        let waypoint = sailed::<ShipWithWaypoint>(TEST_ACTIONS);
+        assert_eq!(waypoint.waypoints().len(), 6);
+        assert_eq!(
+            waypoint.waypoints()[4],
+            (Vector::new(170.0, -38.0), Vector::new(4.0, 10.0))
+        );
+
+        let svg = to_svg(&basic, &waypoint, false);
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+        assert!(svg.trim_end().ends_with("</svg>"));
+        assert!(svg.contains("points=\"0,0 10,0 10,-3 17,-3 17,8\""));
+        assert!(svg.contains("points=\"0,0 100,-10 170,-38 214,72\""));
+        assert_eq!(svg.matches("<circle").count(), 4);
+        assert!(!svg.contains("<line"));
+
+        let svg = to_svg(&basic, &waypoint, true);
+        assert_eq!(svg.matches("<line").count(), 6);
+    }
+
+    fn check_plan(model: Model, target: (i64, i64), allowed: Allowed, expected: &str) {
+        let plan = shortest_plan(model, target, allowed).unwrap();
+        let text = plan.iter().map(|a| format!("{}\n", a)).collect::<String>();
+        assert_eq!(text, expected);
+        let end = match model {
+            Model::Basic => sailed::<BasicShip>(&text).position(),
+            Model::Waypoint => sailed::<ShipWithWaypoint>(&text).position(),
+        };
+        assert_eq!(end, Vector::new(target.0 as f64, target.1 as f64));
+    }
+
+    #[test]
+    fn actions_display_as_parsed() {
+        for text in &["N3", "S0", "E12", "W7", "F10", "L90", "R45", "R270"] {
+            assert_eq!(action(text).unwrap().1.to_string(), *text);
+        }
+    }
+
+    #[test]
+    fn plans_basic_routes() {
+        let only_forward = Allowed::from_letters("FLR");
+        check_plan(Model::Basic, (17, 8), Allowed::ALL, "E17\nS8\n");
+        check_plan(Model::Basic, (17, 8), only_forward, "F17\nR90\nF8\n");
+        check_plan(Model::Basic, (-2, -3), only_forward, "R180\nF2\nR90\nF3\n");
+        check_plan(Model::Basic, (5, 0), Allowed::from_letters("F"), "F5\n");
+        assert_eq!(
+            shortest_plan(Model::Basic, (0, 5), Allowed::from_letters("F")),
+            None
+        );
+        assert_eq!(
+            shortest_plan(Model::Basic, (0, 0), only_forward),
+            Some(vec![])
+        );
+    }
+
+    #[test]
+    fn basic_plans_are_shortest() {
+        // Work out the fewest actions needed for every nearby position by
+        // trying every short list of small actions.
+        let only_forward = Allowed::from_letters("FLR");
+        let moves = ["F1", "F2", "F3", "R90", "R180", "L90"];
+        let moves = moves
+            .iter()
+            .map(|m| action(m).unwrap().1)
+            .collect::<Vec<Action>>();
+        let key = |ship: &BasicShip| {
+            let p = ship.position();
+            (
+                p.x as i64,
+                p.y as i64,
+                ship.facing().x as i64,
+                ship.facing().y as i64,
+            )
+        };
+        let mut fewest = HashMap::new();
+        let mut frontier = vec![BasicShip::default()];
+        for depth in 0..=4 {
+            let mut next = Vec::new();
+            for ship in frontier {
+                let (x, y, _, _) = key(&ship);
+                fewest.entry((x, y)).or_insert(depth);
+                for m in &moves {
+                    next.push(sail(ship.clone(), &[*m]));
+                }
+            }
+            next.sort_by_key(key);
+            next.dedup_by_key(|s| key(s));
+            frontier = next;
+        }
+
+        for x in -3..=3 {
+            for y in -3..=3 {
+                let plan = shortest_plan(Model::Basic, (x, y), only_forward).unwrap();
+                assert_eq!(plan.len(), fewest[&(x, y)], "({}, {})", x, y);
+            }
+        }
+    }
+
+    #[test]
+    fn plans_waypoint_routes() {
+        let only_forward = Allowed::from_letters("FLR");
+        check_plan(Model::Waypoint, (100, -10), Allowed::ALL, "F10\n");
+        check_plan(Model::Waypoint, (10, 100), Allowed::ALL, "R90\nF10\n");
+        check_plan(Model::Waypoint, (-1, -10), Allowed::ALL, "L90\nF1\n");
+        check_plan(Model::Waypoint, (40, -2), Allowed::ALL, "E10\nF2\n");
+        check_plan(Model::Waypoint, (20, 6), Allowed::ALL, "S4\nF2\n");
+        check_plan(Model::Waypoint, (32, 17), Allowed::ALL, "E22\nS18\nF1\n");
+        check_plan(Model::Waypoint, (32, 17), only_forward, "F3\nR90\nF2\n");
+        check_plan(
+            Model::Waypoint,
+            (-32, -17),
+            only_forward,
+            "R180\nF3\nR90\nF2\n",
+        );
+        assert_eq!(shortest_plan(Model::Waypoint, (33, 17), only_forward), None);
+        assert_eq!(
+            shortest_plan(Model::Waypoint, (1, 1), Allowed::from_letters("NSEWLR")),
+            None
+        );
+    }
+}
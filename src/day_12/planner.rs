@@ -0,0 +1,163 @@
+use super::{Action, Direction, Rotation, RotationDirection};
+use std::convert::TryFrom;
+
+/// Which of the two ways of following instructions to plan for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Model {
+    Basic,
+    Waypoint,
+}
+
+/// Which kinds of action a plan may use.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Allowed {
+    pub compass: bool,
+    pub forward: bool,
+    pub rotate: bool,
+}
+
+impl Allowed {
+    pub const ALL: Self = Self {
+        compass: true,
+        forward: true,
+        rotate: true,
+    };
+
+    /// Allows the actions named by their letters, so `FLR` only allows moving
+    /// forward and turning. Any of `N`, `S`, `E` and `W` allows all four
+    /// compass moves.
+    pub fn from_letters(letters: &str) -> Self {
+        Self {
+            compass: letters.contains(|c| "NSEW".contains(c)),
+            forward: letters.contains('F'),
+            rotate: letters.contains(|c| "LR".contains(c)),
+        }
+    }
+}
+
+/// Finds a shortest list of actions that takes a ship from where it starts
+/// to `target`, or `None` if the allowed actions can't get it there.
+///
+/// Plans only turn through multiples of 90 degrees, so the ship stays on
+/// whole-number positions the whole way.
+pub fn shortest_plan(model: Model, target: (i64, i64), allowed: Allowed) -> Option<Vec<Action>> {
+    match (model, target) {
+        (_, (0, 0)) => Some(Vec::new()),
+        (Model::Basic, (x, y)) => basic_plan(x, y, allowed),
+        (Model::Waypoint, (x, y)) => waypoint_plan(x, y, allowed),
+    }
+}
+
+/// Every action moves the ship along at most one axis, so one compass move
+/// for each axis is as good as it gets.
+fn basic_plan(x: i64, y: i64, allowed: Allowed) -> Option<Vec<Action>> {
+    if !allowed.compass {
+        return legs(x, y, allowed);
+    }
+    let mut plan = Vec::new();
+    if x != 0 {
+        plan.push(compass_move(x, Direction::East, Direction::West)?);
+    }
+    if y != 0 {
+        plan.push(compass_move(y, Direction::South, Direction::North)?);
+    }
+    Some(plan)
+}
+
+fn waypoint_plan(x: i64, y: i64, allowed: Allowed) -> Option<Vec<Action>> {
+    if !allowed.forward {
+        return None;
+    }
+    let (wx, wy) = (10, -1);
+    if let Some(times) = multiple(x, y, wx, wy) {
+        return Some(vec![Action::Move(None, amount(times)?)]);
+    }
+    if allowed.rotate {
+        let turned = [(-wy, wx), (-wx, -wy), (wy, -wx)];
+        for (quarters, (rx, ry)) in (1..).zip(&turned) {
+            if let Some(times) = multiple(x, y, *rx, *ry) {
+                let turn = Action::Rotate(quarter_turn(quarters));
+                return Some(vec![turn, Action::Move(None, amount(times)?)]);
+            }
+        }
+    }
+
+    if allowed.compass {
+        // Moving the waypoint along one axis might be enough to line it up
+        // with the target.
+        if y < 0 && x % -y == 0 {
+            let shift = compass_move(x / -y - wx, Direction::East, Direction::West)?;
+            return Some(vec![shift, Action::Move(None, amount(-y)?)]);
+        }
+        if x > 0 && x % wx == 0 && y % (x / wx) == 0 {
+            let shift = compass_move(y / (x / wx) - wy, Direction::South, Direction::North)?;
+            return Some(vec![shift, Action::Move(None, amount(x / wx)?)]);
+        }
+        // Otherwise put the waypoint right on the target and go there once.
+        return Some(vec![
+            compass_move(x - wx, Direction::East, Direction::West)?,
+            compass_move(y - wy, Direction::South, Direction::North)?,
+            Action::Move(None, 1),
+        ]);
+    }
+
+    // Turning only ever points the waypoint along ±(10, -1) or ±(1, 10), so
+    // the target has to be a whole combination of the two.
+    let (ahead, right) = (10 * x - y, x + 10 * y);
+    if ahead % 101 != 0 || right % 101 != 0 {
+        return None;
+    }
+    legs(ahead / 101, right / 101, allowed)
+}
+
+/// Moves forward `ahead` times straight ahead and `right` times after a
+/// turn to the right, with negative amounts going the other way, turning
+/// between legs.
+fn legs(ahead: i64, right: i64, allowed: Allowed) -> Option<Vec<Action>> {
+    let amounts = [ahead, right, -ahead, -right];
+    let mut plan = Vec::new();
+    let mut heading = 0;
+    for (quarters, amt) in amounts.iter().enumerate() {
+        if *amt <= 0 {
+            continue;
+        }
+        if !allowed.forward || (quarters != heading && !allowed.rotate) {
+            return None;
+        }
+        if quarters != heading {
+            plan.push(Action::Rotate(quarter_turn(quarters + 4 - heading)));
+            heading = quarters;
+        }
+        plan.push(Action::Move(None, amount(*amt)?));
+    }
+    Some(plan)
+}
+
+/// How many times `(wx, wy)` has to be added to get to `(x, y)`, if that
+/// works at all.
+fn multiple(x: i64, y: i64, wx: i64, wy: i64) -> Option<i64> {
+    let times = if wx != 0 { x / wx } else { y / wy };
+    Some(times).filter(|t| *t > 0 && t * wx == x && t * wy == y)
+}
+
+fn quarter_turn(quarters: usize) -> Rotation {
+    match quarters % 4 {
+        3 => Rotation {
+            direction: RotationDirection::CounterClockwise,
+            degrees: 90,
+        },
+        q => Rotation {
+            direction: RotationDirection::Clockwise,
+            degrees: 90 * q as u32,
+        },
+    }
+}
+
+fn compass_move(delta: i64, positive: Direction, negative: Direction) -> Option<Action> {
+    let dir = if delta > 0 { positive } else { negative };
+    Some(Action::Move(Some(dir), amount(delta.abs())?))
+}
+
+fn amount(amt: i64) -> Option<u32> {
+    u32::try_from(amt).ok()
+}
@@ -0,0 +1,101 @@
+use super::{BasicShip, Ship, ShipWithWaypoint, Vector};
+use std::fmt::{self, Write};
+
+const WIDTH: f64 = 800.0;
+const BASIC_COLOR: &str = "#1f77b4";
+const WAYPOINT_COLOR: &str = "#d62728";
+
+/// Draws the routes of both ships over each other, with the start marked by
+/// a hollow circle and each end by a filled one. With `show_waypoints`, the
+/// waypoint is drawn as a line from the second ship after every action.
+pub fn to_svg(basic: &BasicShip, waypoint: &ShipWithWaypoint, show_waypoints: bool) -> String {
+    let mut svg = String::new();
+    write_svg(&mut svg, basic, waypoint, show_waypoints).unwrap();
+    svg
+}
+
+fn write_svg<W: Write>(
+    out: &mut W,
+    basic: &BasicShip,
+    waypoint: &ShipWithWaypoint,
+    show_waypoints: bool,
+) -> fmt::Result {
+    let mut corners = vec![basic.bounding_box(), waypoint.bounding_box()];
+    if show_waypoints {
+        corners.extend(waypoint.waypoints().iter().map(|(p, w)| (*p + *w, *p + *w)));
+    }
+    let min = corners.iter().fold(corners[0].0, |m, (c, _)| {
+        Vector::new(m.x.min(c.x), m.y.min(c.y))
+    });
+    let max = corners.iter().fold(corners[0].1, |m, (_, c)| {
+        Vector::new(m.x.max(c.x), m.y.max(c.y))
+    });
+
+    // Leave a margin so the markers at the edges aren't cut off.
+    let extent = (max.x - min.x).max(max.y - min.y).max(1.0);
+    let margin = extent / 40.0;
+    let (x, y) = (min.x - margin, min.y - margin);
+    let (w, h) = (max.x - min.x + 2.0 * margin, max.y - min.y + 2.0 * margin);
+    let radius = extent / 150.0;
+
+    writeln!(
+        out,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"{} {} {} {}\" width=\"{}\" height=\"{}\">",
+        x,
+        y,
+        w,
+        h,
+        WIDTH,
+        (WIDTH * h / w).round()
+    )?;
+    writeln!(
+        out,
+        "  <rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"white\"/>",
+        x, y, w, h
+    )?;
+
+    if show_waypoints {
+        writeln!(
+            out,
+            "  <g stroke=\"{}\" stroke-opacity=\"0.3\">",
+            WAYPOINT_COLOR
+        )?;
+        for (position, offset) in waypoint.waypoints() {
+            let end = *position + *offset;
+            writeln!(
+                out,
+                "    <line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" vector-effect=\"non-scaling-stroke\"/>",
+                position.x, position.y, end.x, end.y
+            )?;
+        }
+        writeln!(out, "  </g>")?;
+    }
+
+    write_route(out, basic.path(), BASIC_COLOR, radius)?;
+    write_route(out, waypoint.path(), WAYPOINT_COLOR, radius)?;
+    writeln!(out, "</svg>")
+}
+
+fn write_route<W: Write>(out: &mut W, path: &[Vector], color: &str, radius: f64) -> fmt::Result {
+    let points = path
+        .iter()
+        .map(|p| format!("{},{}", p.x, p.y))
+        .collect::<Vec<_>>();
+    writeln!(
+        out,
+        "  <polyline points=\"{}\" fill=\"none\" stroke=\"{}\" stroke-width=\"2\" vector-effect=\"non-scaling-stroke\"/>",
+        points.join(" "),
+        color
+    )?;
+    let (start, end) = (path[0], path[path.len() - 1]);
+    writeln!(
+        out,
+        "  <circle cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"white\" stroke=\"{}\" vector-effect=\"non-scaling-stroke\"/>",
+        start.x, start.y, radius, color
+    )?;
+    writeln!(
+        out,
+        "  <circle cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"{}\"/>",
+        end.x, end.y, radius, color
+    )
+}
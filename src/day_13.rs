@@ -1,80 +1,71 @@
-use num::{
-    integer::{self, ExtendedGcd},
-    Integer,
-};
+use self::schedule::Schedule;
+
+mod schedule;
 
 pub fn star_1(data: String) {
     let (timestamp, ids) = parse(&data);
-    for t in timestamp.. {
-        for id in ids.iter().filter_map(|x| *x) {
-            if t % id == 0 {
-                println!("{}", (t - timestamp) * id);
-                return;
-            }
-        }
+    let schedule = match build_schedule(&ids) {
+        Some(schedule) => schedule,
+        None => return,
+    };
+    match schedule.earliest(timestamp) {
+        Some((id, time)) => println!("{}", (time - timestamp) * id),
+        None => println!("No buses are in service."),
     }
 }
 
 pub fn star_2(data: String) {
     let (_, ids) = parse(&data);
-
-    let ids = ids
-        .iter()
-        .enumerate()
-        .filter_map(|(i, x)| x.map(|x| (i as i64, x as i64)))
-        .map(|(a, b)| ((b - a) % b, b))
-        .collect::<Vec<_>>();
-
-    println!("{:?}", ids);
-
-    // Verify all values are coprime.
-    if ids
-        .iter()
-        .zip(ids.iter())
-        .filter(|(x, y)| x.1 != y.1)
-        .any(|(x, y)| integer::gcd(x.1, y.1) != 1)
-    {
-        panic!("Values aren't coprime.");
+    let schedule = match build_schedule(&ids) {
+        Some(schedule) => schedule,
+        None => return,
+    };
+    match schedule.earliest_in_sequence() {
+        Some(timestamp) => println!("{}", timestamp),
+        None => println!("The buses never line up."),
     }
-
-    let rem = chinese_remainder(&ids[..]).unwrap();
-
-    println!("{}", rem);
 }
 
-fn egcd(a: i64, b: i64) -> (i64, i64, i64) {
-    if a == 0 {
-        (b, 0, 1)
+/// Prints which buses leave when, by default from a few minutes before the
+/// time in the notes until the first bus leaves. With `list`, prints each
+/// departure on its own line rather than a grid.
+pub fn timetable(data: String, args: &[String]) {
+    let (timestamp, ids) = parse(&data);
+    let schedule = match build_schedule(&ids) {
+        Some(schedule) => schedule,
+        None => return,
+    };
+    let list = args.first().is_some_and(|a| a == "list");
+    let window = match &args[list as usize..] {
+        [start, end, ..] => start.parse().unwrap()..end.parse::<u64>().unwrap() + 1,
+        _ => match schedule.earliest(timestamp) {
+            Some((_, first)) => timestamp.saturating_sub(10)..first + 1,
+            None => {
+                println!("No buses are in service.");
+                return;
+            }
+        },
+    };
+    if list {
+        for (time, id) in schedule.departures_within(window) {
+            println!("{}: bus {}", time, id);
+        }
     } else {
-        let (g, x, y) = egcd(b % a, a);
-        (g, y - (b / a) * x, x)
+        print!("{}", schedule.timetable(window));
     }
 }
 
-fn mod_inv(x: i64, n: i64) -> Option<i64> {
-    let (g, x, _) = egcd(x, n);
-    if g == 1 {
-        Some((x % n + n) % n)
-    } else {
-        None
+fn build_schedule(ids: &[Option<u32>]) -> Option<Schedule> {
+    let schedule = Schedule::new(ids);
+    if schedule.is_none() {
+        println!("Bus ids can't be 0.");
     }
+    schedule
 }
 
-fn chinese_remainder(mods: &[(i64, i64)]) -> Option<i64> {
-    let prod = mods.iter().map(|(_, m)| m).product::<i64>();
-    let sum = mods
-        .iter()
-        .map(|(r, m)| {
-            let p = prod / m;
-            r * mod_inv(p, *m).unwrap() * p
-        })
-        .sum::<i64>();
-    Some(sum % prod)
-}
-
-fn parse(data: &str) -> (u32, Vec<Option<u32>>) {
+fn parse(data: &str) -> (u64, Vec<Option<u32>>) {
     let mut lines = data.lines().map(str::trim).filter(|s| !s.is_empty());
-    let timestamp = lines.next().unwrap().parse::<u32>().unwrap();
+    let timestamp = lines.next().unwrap().parse::<u64>().unwrap();
     let ids = lines
         .next()
         .unwrap()
@@ -89,3 +80,106 @@ fn parse(data: &str) -> (u32, Vec<Option<u32>>) {
         .collect();
     (timestamp, ids)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{parse, schedule::Schedule};
+    use crate::modmath::chinese_remainder;
+    use num::BigInt;
+
+    const TEST_NOTES: &str = include_str!("../data/day_13_test.txt");
+
+    fn solve(congruences: &[(i64, i64)]) -> Option<(BigInt, BigInt)> {
+        let congruences = congruences
+            .iter()
+            .map(|(r, m)| (BigInt::from(*r), BigInt::from(*m)));
+        chinese_remainder(congruences)
+    }
+
+    fn solution(r: i64, m: i64) -> Option<(BigInt, BigInt)> {
+        Some((BigInt::from(r), BigInt::from(m)))
+    }
+
+    #[test]
+    fn solves_coprime_moduli() {
+        let (_, ids) = parse(TEST_NOTES);
+        let congruences = ids
+            .iter()
+            .enumerate()
+            .filter_map(|(i, id)| id.map(|id| (-(i as i64), i64::from(id))))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            solve(&congruences),
+            solution(1068781, 7 * 13 * 59 * 31 * 19)
+        );
+        assert_eq!(solve(&[]), solution(0, 1));
+    }
+
+    #[test]
+    fn solves_shared_factors() {
+        assert_eq!(solve(&[(2, 4), (4, 6)]), solution(10, 12));
+        assert_eq!(solve(&[(3, 6), (3, 6), (1, 2)]), solution(3, 6));
+        assert_eq!(solve(&[(1, 4), (2, 6)]), None);
+    }
+
+    #[test]
+    fn solves_without_overflow() {
+        let primes = [1_000_000_007, 1_000_000_009, 998_244_353, 2_147_483_647];
+        let congruences = primes.iter().map(|p| (p - 1, *p)).collect::<Vec<_>>();
+        let product = primes.iter().map(|p| BigInt::from(*p)).product::<BigInt>();
+        assert_eq!(solve(&congruences), Some((&product - 1, product)));
+    }
+
+    #[test]
+    fn schedule_finds_departures() {
+        let (timestamp, ids) = parse(TEST_NOTES);
+        let schedule = Schedule::new(&ids).unwrap();
+        assert_eq!(
+            schedule.next_departures(timestamp).collect::<Vec<_>>(),
+            vec![(7, 945), (13, 949), (59, 944), (31, 961), (19, 950)]
+        );
+        assert_eq!(schedule.earliest(timestamp), Some((59, 944)));
+        assert_eq!(schedule.earliest(945), Some((7, 945)));
+        assert_eq!(
+            schedule.departures_within(938..950),
+            vec![(938, 7), (944, 59), (945, 7), (949, 13)]
+        );
+    }
+
+    #[test]
+    fn schedule_aligns_buses() {
+        let (_, ids) = parse(TEST_NOTES);
+        let schedule = Schedule::new(&ids).unwrap();
+        assert_eq!(schedule.earliest_in_sequence(), Some(BigInt::from(1068781)));
+        let subset = vec![(7, 0), (13, 2), (19, 3)];
+        assert_eq!(schedule.earliest_aligned(subset), Some(BigInt::from(700)));
+        assert_eq!(schedule.earliest_aligned(vec![(17, 0), (13, 2)]), None);
+
+        let shared = Schedule::new(&[Some(4), Some(6)]).unwrap();
+        assert_eq!(
+            shared.earliest_aligned(vec![(4, 0), (6, 2)]),
+            Some(BigInt::from(4))
+        );
+        assert_eq!(shared.earliest_aligned(vec![(4, 0), (6, 1)]), None);
+    }
+
+    #[test]
+    fn schedule_rejects_bus_zero() {
+        let (_, ids) = parse("939\n7,0,13");
+        assert_eq!(Schedule::new(&ids), None);
+        assert!(Schedule::new(&[Some(7), None, Some(13)]).is_some());
+    }
+
+    #[test]
+    fn schedule_draws_timetable() {
+        let (_, ids) = parse(TEST_NOTES);
+        let timetable = Schedule::new(&ids).unwrap().timetable(943..946);
+        assert_eq!(
+            timetable,
+            "time   bus 7   bus 13  bus 59  bus 31  bus 19\n\
+             943      .       .       .       .       .\n\
+             944      .       .       D       .       .\n\
+             945      D       .       .       .       .\n"
+        );
+    }
+}
@@ -0,0 +1,121 @@
+use crate::modmath::chinese_remainder;
+use num::{BigInt, Integer};
+use std::{fmt::Write, ops::Range};
+
+/// A bus in service, which leaves at every multiple of its id.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Bus {
+    pub id: u64,
+    /// Where the bus appears in the list of ids.
+    pub position: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Schedule {
+    buses: Vec<Bus>,
+}
+
+impl Schedule {
+    /// Takes the list of ids from the notes, where `None` is a bus that's out
+    /// of service. Returns `None` if a bus has id 0, as it would never leave.
+    pub fn new(ids: &[Option<u32>]) -> Option<Self> {
+        if ids.contains(&Some(0)) {
+            return None;
+        }
+        let buses = ids
+            .iter()
+            .enumerate()
+            .filter_map(|(position, id)| {
+                id.map(|id| Bus {
+                    id: u64::from(id),
+                    position,
+                })
+            })
+            .collect();
+        Some(Self { buses })
+    }
+
+    /// The first time at or after `t` that each bus leaves, as `(id, time)`.
+    pub fn next_departures(&self, t: u64) -> impl Iterator<Item = (u64, u64)> + '_ {
+        self.buses
+            .iter()
+            .map(move |bus| (bus.id, next_departure(bus.id, t)))
+    }
+
+    /// The first bus to leave at or after `t`, as `(id, time)`.
+    pub fn earliest(&self, t: u64) -> Option<(u64, u64)> {
+        self.next_departures(t).min_by_key(|(_, time)| *time)
+    }
+
+    /// Every departure in the window, as `(time, id)` in order of time.
+    pub fn departures_within(&self, window: Range<u64>) -> Vec<(u64, u64)> {
+        let mut departures = Vec::new();
+        for bus in &self.buses {
+            let first = next_departure(bus.id, window.start);
+            let times = (first..window.end).step_by(bus.id as usize);
+            departures.extend(times.map(|time| (time, bus.id)));
+        }
+        departures.sort_unstable();
+        departures
+    }
+
+    /// The earliest time `t` at which each bus `id` leaves at `t + offset`,
+    /// or `None` if they never line up like that or one of the ids isn't a
+    /// bus in the schedule.
+    pub fn earliest_aligned<I>(&self, offsets: I) -> Option<BigInt>
+    where
+        I: IntoIterator<Item = (u64, u64)>,
+    {
+        let mut congruences = Vec::new();
+        for (id, offset) in offsets {
+            if !self.buses.iter().any(|bus| bus.id == id) {
+                return None;
+            }
+            let id = BigInt::from(id);
+            congruences.push(((-BigInt::from(offset)).mod_floor(&id), id));
+        }
+        chinese_remainder(congruences).map(|(t, _)| t)
+    }
+
+    /// The earliest time at which each bus leaves as many minutes after it
+    /// as its position in the list.
+    pub fn earliest_in_sequence(&self) -> Option<BigInt> {
+        self.earliest_aligned(self.buses.iter().map(|b| (b.id, b.position as u64)))
+    }
+
+    /// Draws which buses leave at each time in the window, marking
+    /// departures with `D`.
+    pub fn timetable(&self, window: Range<u64>) -> String {
+        let time_width = (window.end.to_string().len() + 2).max(7);
+        let labels = self
+            .buses
+            .iter()
+            .map(|bus| format!("bus {}", bus.id))
+            .collect::<Vec<_>>();
+
+        let mut table = format!("{:<1$}", "time", time_width);
+        for label in &labels {
+            write!(table, "{:<1$}", label, (label.len() + 2).max(8)).unwrap();
+        }
+        trim_line(&mut table);
+
+        for t in window {
+            write!(table, "{:<1$}", t, time_width).unwrap();
+            for (bus, label) in self.buses.iter().zip(&labels) {
+                let mark = if t % bus.id == 0 { 'D' } else { '.' };
+                write!(table, "  {:<1$}", mark, label.len().max(6)).unwrap();
+            }
+            trim_line(&mut table);
+        }
+        table
+    }
+}
+
+fn next_departure(id: u64, t: u64) -> u64 {
+    t + (id - t % id) % id
+}
+
+fn trim_line(text: &mut String) {
+    text.truncate(text.trim_end().len());
+    text.push('\n');
+}
@@ -1,10 +1,9 @@
 use crate::utils::u64_;
-use itertools::Itertools;
 use nom::{
     branch::alt,
     bytes::complete::tag,
-    character::complete::{digit1, space0},
-    combinator::{map, map_res, value},
+    character::complete::space0,
+    combinator::{map, value, verify},
     multi::many1,
     IResult,
 };
@@ -12,7 +11,7 @@ use std::{collections::HashMap, fmt};
 
 pub fn star_1(data: String) {
     let instructions = parse(&data);
-    let mut state = State::default();
+    let mut state = State::new(word_width(&instructions));
     for ins in instructions {
         state.step(ins);
     }
@@ -21,13 +20,55 @@ pub fn star_1(data: String) {
 
 pub fn star_2(data: String) {
     let instructions = parse(&data);
-    let mut state = State::default();
+    let mut state = State::new(word_width(&instructions));
     for ins in instructions {
         state.step_v2(ins);
     }
     println!("{}", state.total());
 }
 
+/// Runs the program, showing how the mask changes each value written, or
+/// with `v2` each address.
+pub fn trace(data: String, args: &[String]) {
+    let v2 = args.iter().any(|a| a == "v2");
+    let instructions = parse(&data);
+    let mut state = State::new(word_width(&instructions));
+    for ins in instructions {
+        if let Instruction::SetMem(mem) = ins {
+            print!("{}", describe_write(state.mask, mem, v2));
+        }
+        if v2 {
+            state.step_v2(ins);
+        } else {
+            state.step(ins);
+        }
+    }
+    println!("total: {}", state.total());
+}
+
+/// Lines up a write with the mask and what it turns the value, or for `v2`
+/// the address, into.
+fn describe_write(mask: Mask, mem: Mem, v2: bool) -> String {
+    let width = mask.width as usize;
+    let (label, before, after) = if v2 {
+        let addresses = mask.floating_addresses(mem.address);
+        ("address", mem.address, addresses.to_string())
+    } else {
+        let value = mask.apply_to(mem.value);
+        let after = format!("{:0width$b}  (decimal {})", value, value, width = width);
+        ("value", mem.value, after)
+    };
+    format!(
+        "{}\n  {:<7}  {:0width$b}\n  mask     {}\n  result   {}\n",
+        Instruction::SetMem(mem),
+        label,
+        before,
+        mask,
+        after,
+        width = width
+    )
+}
+
 fn parse(data: &str) -> Vec<Instruction> {
     data.lines()
         .map(str::trim)
@@ -36,16 +77,49 @@ fn parse(data: &str) -> Vec<Instruction> {
         .collect()
 }
 
+const DEFAULT_WIDTH: u32 = 36;
+
+/// The width of the program's masks, which had better all be the same.
+fn word_width(instructions: &[Instruction]) -> u32 {
+    instructions
+        .iter()
+        .find_map(|ins| match ins {
+            Instruction::SetMask(mask) => Some(mask.width),
+            Instruction::SetMem(_) => None,
+        })
+        .unwrap_or(DEFAULT_WIDTH)
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 struct State {
+    width: u32,
     mask: Mask,
     memory: HashMap<u64, u64>,
+    floating: FloatingMemory,
 }
 
 impl State {
+    /// Starts with a mask that leaves everything alone.
+    fn new(width: u32) -> Self {
+        Self {
+            width,
+            mask: Mask::new(width),
+            memory: HashMap::new(),
+            floating: FloatingMemory::default(),
+        }
+    }
+
+    fn set_mask(&mut self, mask: Mask) {
+        assert_eq!(
+            mask.width, self.width,
+            "The mask doesn't match the word width."
+        );
+        self.mask = mask;
+    }
+
     fn step(&mut self, ins: Instruction) {
         match ins {
-            Instruction::SetMask(mask) => self.mask = mask,
+            Instruction::SetMask(mask) => self.set_mask(mask),
             Instruction::SetMem(mem) => {
                 let value = self.mask.apply_to(mem.value);
                 *self.memory.entry(mem.address).or_insert(0) = value;
@@ -55,27 +129,28 @@ impl State {
 
     fn step_v2(&mut self, ins: Instruction) {
         match ins {
-            Instruction::SetMask(mask) => self.mask = mask,
+            Instruction::SetMask(mask) => self.set_mask(mask),
             Instruction::SetMem(mem) => {
-                let addresses = self.mask.floating_masks().map(|m| m.apply_to(mem.address));
-                for address in addresses {
-                    *self.memory.entry(address).or_insert(0) = mem.value;
-                }
+                let addresses = self.mask.floating_addresses(mem.address);
+                self.floating.write(addresses, mem.value);
             }
         }
     }
 
-    fn total(&self) -> u64 {
-        self.memory.values().sum()
+    /// The sum of everything in memory. Each decoder only writes to its own
+    /// kind of memory, so this works for both.
+    ///
+    /// Up to 2^64 addresses can each hold a value just under 2^64, so the sum
+    /// needs 128 bits.
+    fn total(&self) -> u128 {
+        let memory = self.memory.values().map(|v| u128::from(*v)).sum::<u128>();
+        memory + self.floating.total()
     }
 }
 
 impl Default for State {
     fn default() -> Self {
-        Self {
-            mask: Mask::default(),
-            memory: HashMap::default(),
-        }
+        Self::new(DEFAULT_WIDTH)
     }
 }
 
@@ -85,6 +160,15 @@ enum Instruction {
     SetMem(Mem),
 }
 
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Instruction::SetMask(mask) => write!(f, "mask = {}", mask),
+            Instruction::SetMem(mem) => write!(f, "{}", mem),
+        }
+    }
+}
+
 fn instruction(input: &str) -> IResult<&str, Instruction> {
     alt((
         map(set_mask, Instruction::SetMask),
@@ -92,47 +176,116 @@ fn instruction(input: &str) -> IResult<&str, Instruction> {
     ))(input)
 }
 
+/// A mask over the lowest `width` bits of a word, where `mask` has a bit set
+/// for each bit that's `0` or `1` rather than `X`, and `substrate` holds
+/// those bits.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 struct Mask {
+    width: u32,
     substrate: u64,
     mask: u64,
 }
 
 impl Mask {
+    /// A mask that's all `X`.
+    fn new(width: u32) -> Self {
+        Self {
+            width,
+            substrate: 0,
+            mask: 0,
+        }
+    }
+
+    /// Every bit of the word.
+    fn word(self) -> u64 {
+        u64::MAX.checked_shr(64 - self.width).unwrap_or(0)
+    }
+
     fn apply_to(self, value: u64) -> u64 {
-        (self.substrate & self.mask) | (value & !self.mask)
+        ((self.substrate & self.mask) | (value & !self.mask)) & self.word()
     }
 
-    fn floating_masks(self) -> impl Iterator<Item = Self> {
-        let digits = (0..36)
-            .filter(|x| (1 << x) & self.mask == 0)
-            .collect::<Vec<_>>();
+    /// The addresses a version 2 decoder writes to, as a pattern where the
+    /// mask's fixed bits are the fixed bits of the addresses.
+    fn floating_addresses(self, address: u64) -> Self {
+        Self {
+            substrate: (address | self.substrate) & self.mask,
+            ..self
+        }
+    }
 
-        let mask = self.substrate | !self.mask;
+    /// How many values this matches, treating it as an address pattern.
+    fn len(self) -> u128 {
+        1 << (self.width - self.mask.count_ones())
+    }
 
-        (0..=digits.len())
-            .map(move |c| digits.clone().into_iter().combinations(c))
-            .flatten()
-            .map(number_with_digits)
-            .map(move |floating| Self {
-                substrate: self.substrate | floating,
-                mask,
-            })
+    fn intersects(self, other: Self) -> bool {
+        (self.substrate ^ other.substrate) & self.mask & other.mask == 0
+    }
+
+    /// Splits the addresses this matches that `other` doesn't into disjoint
+    /// patterns, by fixing each bit `other` fixes but this doesn't in turn.
+    fn subtract(self, other: Self) -> Vec<Self> {
+        if !self.intersects(other) {
+            return vec![self];
+        }
+        let mut pieces = Vec::new();
+        let mut rest = self;
+        for i in 0..self.width {
+            let bit = 1 << i;
+            if other.mask & !self.mask & bit != 0 {
+                pieces.push(Self {
+                    substrate: rest.substrate | (!other.substrate & bit),
+                    mask: rest.mask | bit,
+                    ..rest
+                });
+                rest = Self {
+                    substrate: rest.substrate | (other.substrate & bit),
+                    mask: rest.mask | bit,
+                    ..rest
+                };
+            }
+        }
+        pieces
     }
 }
 
-impl Default for Mask {
-    fn default() -> Self {
-        Self {
-            substrate: 0,
-            mask: 0,
+/// Memory written through address patterns, without expanding them.
+///
+/// The patterns in memory never overlap: each write first takes its
+/// addresses out of every earlier pattern it overlaps, so the total is just
+/// each value times the size of its pattern, however many floating bits the
+/// patterns have.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+struct FloatingMemory {
+    writes: Vec<(Mask, u64)>,
+}
+
+impl FloatingMemory {
+    fn write(&mut self, addresses: Mask, value: u64) {
+        let writes = self.writes.drain(..).flat_map(|(pattern, old)| {
+            pattern
+                .subtract(addresses)
+                .into_iter()
+                .map(move |piece| (piece, old))
+        });
+        self.writes = writes.collect();
+        if value != 0 {
+            self.writes.push((addresses, value));
         }
     }
+
+    fn total(&self) -> u128 {
+        self.writes
+            .iter()
+            .map(|(pattern, value)| pattern.len() * u128::from(*value))
+            .sum()
+    }
 }
 
 impl fmt::Display for Mask {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        for i in (0..36).rev() {
+        for i in (0..self.width).rev() {
             if self.mask & (1 << i) == 0 {
                 write!(f, "X")?;
             } else if self.substrate & (1 << i) == 0 {
@@ -155,19 +308,27 @@ fn set_mask(input: &str) -> IResult<&str, Mask> {
 }
 
 fn mask(input: &str) -> IResult<&str, Mask> {
-    let (input, bits) = many1(mask_bit)(input)?;
+    let (input, bits) = verify(many1(mask_bit), |bits: &[_]| bits.len() <= 64)(input)?;
+    let width = bits.len() as u32;
 
     let mut substrate = 0;
     let mut mask = 0;
     for bit in bits {
-        substrate = substrate << 1;
-        mask = mask << 1;
+        substrate <<= 1;
+        mask <<= 1;
         if let Some(bit) = bit {
             substrate = (substrate & !1) | if bit { 1 } else { 0 };
-            mask = mask | 1;
+            mask |= 1;
         }
     }
-    Ok((input, Mask { substrate, mask }))
+    Ok((
+        input,
+        Mask {
+            width,
+            substrate,
+            mask,
+        },
+    ))
 }
 
 fn mask_bit(input: &str) -> IResult<&str, Option<bool>> {
@@ -184,6 +345,12 @@ struct Mem {
     value: u64,
 }
 
+impl fmt::Display for Mem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "mem[{}] = {}", self.address, self.value)
+    }
+}
+
 fn set_mem(input: &str) -> IResult<&str, Mem> {
     let (input, _) = space0(input)?;
     let (input, _) = tag("mem")(input)?;
@@ -200,9 +367,144 @@ fn set_mem(input: &str) -> IResult<&str, Mem> {
     Ok((input, Mem { address, value }))
 }
 
-fn number_with_digits<I>(digits: I) -> u64
-where
-    I: IntoIterator<Item = u64>,
-{
-    digits.into_iter().fold(0, |acc, d| acc | (1 << d))
+#[cfg(test)]
+mod tests {
+    use super::{describe_write, parse, word_width, FloatingMemory, Instruction, State};
+    use std::collections::HashMap;
+
+    fn run_v2(program: &str) -> State {
+        let mut state = State::default();
+        for ins in parse(program) {
+            state.step_v2(ins);
+        }
+        state
+    }
+
+    #[test]
+    fn decodes_floating_addresses() {
+        let state = run_v2(include_str!("../data/day_14_test_2.txt"));
+        assert_eq!(state.total(), 208);
+    }
+
+    #[test]
+    fn floating_memory_matches_expanded_writes() {
+        let program = "\
+            mask = 000000000000000000000000000000XX1X0X\n\
+            mem[5] = 7\n\
+            mem[16] = 3\n\
+            mask = 0000000000000000000000000000000X0XX1\n\
+            mem[40] = 11\n\
+            mask = 000000000000000000000000000000XXX000\n\
+            mem[3] = 0\n\
+            mem[1] = 2\n";
+
+        // Write to every address each pattern matches, one at a time.
+        let mut expanded = HashMap::new();
+        let mut mask = None;
+        for ins in parse(program) {
+            match ins {
+                Instruction::SetMask(m) => mask = Some(m),
+                Instruction::SetMem(mem) => {
+                    let pattern = mask.unwrap().floating_addresses(mem.address);
+                    for address in 0..1 << 8 {
+                        if (address ^ pattern.substrate) & pattern.mask == 0 {
+                            expanded.insert(address, mem.value);
+                        }
+                    }
+                }
+            }
+        }
+
+        let expected = expanded.values().map(|v| u128::from(*v)).sum::<u128>();
+        assert_eq!(run_v2(program).total(), expected);
+    }
+
+    #[test]
+    fn floating_memory_handles_many_floating_bits() {
+        let mut state = run_v2(&format!("mask = {}00\nmem[0] = 1", "X".repeat(34)));
+        assert_eq!(state.total(), 1 << 34);
+
+        for ins in parse(&format!("mask = {}1\nmem[0] = 2", "X".repeat(35))) {
+            state.step_v2(ins);
+        }
+        assert_eq!(state.total(), (1 << 34) + (2 << 35));
+
+        for ins in parse(&format!("mask = {}\nmem[0] = 3", "X".repeat(36))) {
+            state.step_v2(ins);
+        }
+        assert_eq!(
+            state.floating,
+            FloatingMemory {
+                writes: vec![(state.floating.writes[0].0, 3)]
+            }
+        );
+        assert_eq!(state.total(), 3 << 36);
+    }
+
+    #[test]
+    fn floating_totals_do_not_overflow() {
+        let value = (1 << 30) - 1;
+        let state = run_v2(&format!("mask = {}\nmem[0] = {}", "X".repeat(36), value));
+        assert_eq!(state.total(), value << 36);
+
+        let value = u64::MAX;
+        let mut state = State::new(64);
+        for ins in parse(&format!("mask = {}\nmem[0] = {}", "X".repeat(64), value)) {
+            state.step_v2(ins);
+        }
+        assert_eq!(state.total(), u128::from(value) << 64);
+    }
+
+    #[test]
+    fn programs_round_trip() {
+        for program in &[
+            include_str!("../data/day_14_test.txt"),
+            include_str!("../data/day_14_test_2.txt"),
+        ] {
+            let text = parse(program)
+                .iter()
+                .map(|ins| format!("{}\n", ins))
+                .collect::<String>();
+            assert_eq!(text, *program);
+        }
+    }
+
+    #[test]
+    fn masks_have_any_width() {
+        let program = "mask = 1X0X\nmem[3] = 255\nmem[1] = 4\n";
+        let instructions = parse(program);
+        assert_eq!(word_width(&instructions), 4);
+        let mut state = State::new(4);
+        for ins in instructions.iter().copied() {
+            state.step(ins);
+        }
+        // 255 only keeps its lowest four bits.
+        assert_eq!(state.total(), 0b1101 + 0b1100);
+
+        let mut state = State::new(4);
+        for ins in instructions {
+            state.step_v2(ins);
+        }
+        // 1X1X and 1X0X each cover four addresses.
+        assert_eq!(state.total(), 4 * 255 + 4 * 4);
+
+        assert!(super::mask(&"X".repeat(64)).is_ok());
+        assert!(super::mask(&"X".repeat(65)).is_err());
+    }
+
+    #[test]
+    fn describes_writes() {
+        let (mask, mem) = match &parse("mask = 1X0X\nmem[3] = 6")[..] {
+            [Instruction::SetMask(mask), Instruction::SetMem(mem)] => (*mask, *mem),
+            _ => unreachable!(),
+        };
+        assert_eq!(
+            describe_write(mask, mem, false),
+            "mem[3] = 6\n  value    0110\n  mask     1X0X\n  result   1100  (decimal 12)\n"
+        );
+        assert_eq!(
+            describe_write(mask, mem, true),
+            "mem[3] = 6\n  address  0011\n  mask     1X0X\n  result   1X1X\n"
+        );
+    }
 }
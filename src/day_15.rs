@@ -1,25 +1,164 @@
-use std::collections::HashMap;
+use rayon::prelude::*;
+use std::{
+    collections::HashMap,
+    io::{self, BufWriter, Write},
+    time::Instant,
+};
+
+use self::game::Game;
+
+mod game;
 
 pub fn star_1(data: String) {
-    for nums in parse(&data) {
-        println!("{}", Nums::new(nums).nth(2020 - 1).unwrap());
+    for num in play_all(&parse(&data), 2020) {
+        println!("{}", num);
     }
 }
 
 pub fn star_2(data: String) {
+    for num in play_all(&parse(&data), 30000000) {
+        println!("{}", num);
+    }
+}
+
+/// Plays each game for the given number of turns, along with any extra
+/// starting numbers given like `0,3,6`. With `stream`, prints every number
+/// spoken rather than just the last.
+pub fn play(data: String, args: &[String]) {
+    let turns = match turn_count(args.first()) {
+        Some(turns) => turns,
+        None => return,
+    };
+    let stream = args[1..].iter().any(|a| a == "stream");
+    let mut games = parse(&data);
+    games.extend(
+        args[1..]
+            .iter()
+            .filter(|a| *a != "stream")
+            .map(|a| starting(a)),
+    );
+
+    if stream {
+        // Stop quietly if whatever's reading the stream stops early.
+        let _ = write_spoken(&games, turns);
+    } else {
+        for (nums, num) in games.iter().zip(play_all(&games, turns)) {
+            println!("{}: {}", join(nums), num);
+        }
+    }
+}
+
+fn write_spoken(games: &[Vec<u32>], turns: u32) -> io::Result<()> {
+    let stdout = io::stdout();
+    let mut out = BufWriter::new(stdout.lock());
+    for nums in games {
+        writeln!(out, "# {}", join(nums))?;
+        for num in spoken(nums, turns) {
+            writeln!(out, "{}", num)?;
+        }
+    }
+    out.flush()
+}
+
+/// The number spoken on the given turn of each game, playing the games in
+/// parallel.
+fn play_all(games: &[Vec<u32>], turns: u32) -> Vec<u32> {
+    games
+        .par_iter()
+        .map(|nums| Game::new(nums).jump_to(turns))
+        .collect()
+}
+
+/// Every number spoken up to the given turn.
+fn spoken(nums: &[u32], turns: u32) -> impl Iterator<Item = u32> {
+    Game::new(nums).take(turns as usize)
+}
+
+fn join(nums: &[u32]) -> String {
+    nums.iter()
+        .map(u32::to_string)
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Times the dense engine against the original iterator for each line of
+/// starting numbers.
+pub fn bench(data: String, args: &[String]) {
+    let turns = if args.is_empty() {
+        30000000
+    } else {
+        match turn_count(args.first()) {
+            Some(turns) => turns,
+            None => return,
+        }
+    };
+    for nums in parse(&data) {
+        let start = Instant::now();
+        let game = Game::new(&nums).jump_to(turns);
+        let game_time = start.elapsed();
+
+        let start = Instant::now();
+        let iter = Nums::new(nums.iter().copied().map(u64::from).collect())
+            .nth(turns as usize - 1)
+            .unwrap();
+        let iter_time = start.elapsed();
+
+        assert_eq!(u64::from(game), iter);
+        println!(
+            "{:?}: {} (game {:?}, iterator {:?}, {:.1}x)",
+            nums,
+            game,
+            game_time,
+            iter_time,
+            iter_time.as_secs_f64() / game_time.as_secs_f64()
+        );
+    }
+}
+
+/// Prints the first turn each of the given numbers is spoken on, within the
+/// given number of turns.
+pub fn first(data: String, args: &[String]) {
+    let turns = match turn_count(args.first()) {
+        Some(turns) => turns,
+        None => return,
+    };
     for nums in parse(&data) {
-        println!("{}", Nums::new(nums).nth(30000000 - 1).unwrap());
+        let mut game = Game::new(&nums).with_first_turns();
+        game.jump_to(turns);
+        println!("{:?}:", nums);
+        for num in &args[1..] {
+            match game.first_turn(num.parse().unwrap()) {
+                Some(turn) => println!("  {} on turn {}", num, turn),
+                None => println!("  {} not by turn {}", num, turns),
+            }
+        }
+    }
+}
+
+/// Reads how many turns to play, which has to be at least one.
+fn turn_count(arg: Option<&String>) -> Option<u32> {
+    let turns = arg
+        .and_then(|arg| arg.parse().ok())
+        .filter(|turns| *turns > 0);
+    if turns.is_none() {
+        println!("Expected a number of turns of at least 1.");
     }
+    turns
 }
 
-fn parse(data: &str) -> Vec<Vec<u64>> {
+fn parse(data: &str) -> Vec<Vec<u32>> {
     data.lines()
         .map(str::trim)
         .filter(|s| !s.is_empty())
-        .map(|s| s.split(',').map(|s| s.parse::<u64>().unwrap()).collect())
+        .map(starting)
         .collect()
 }
 
+fn starting(line: &str) -> Vec<u32> {
+    line.split(',').map(|s| s.parse::<u32>().unwrap()).collect()
+}
+
+/// The original engine, kept to check and benchmark the game against.
 #[derive(Debug, Clone, PartialEq, Eq)]
 struct Nums {
     timestamp: u64,
@@ -71,13 +210,6 @@ enum Inner {
 }
 
 impl Inner {
-    fn try_as_slice(&self) -> Option<&[u64]> {
-        match self {
-            Self::List(ns) => Some(ns),
-            Self::Last(_) => None,
-        }
-    }
-
     fn value_at_timestamp(&self, timestamp: u64) -> u64 {
         match self {
             Self::List(ns) => ns[timestamp as usize],
@@ -85,3 +217,64 @@ impl Inner {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{game::Game, parse, play_all, spoken, starting, Nums};
+
+    #[test]
+    fn game_matches_examples() {
+        let expected = [436, 1, 10, 27, 78, 438, 1836];
+        for (nums, expected) in parse(include_str!("../data/day_15_test.txt"))
+            .iter()
+            .zip(&expected)
+        {
+            assert_eq!(Game::new(nums).jump_to(2020), *expected);
+        }
+    }
+
+    #[test]
+    fn game_matches_iterator() {
+        let starts: [&[u32]; 4] = [&[0, 3, 6], &[7], &[1_000_000, 5, 1_000_000], &[u32::MAX, 0]];
+        for nums in &starts {
+            let reference = Nums::new(nums.iter().copied().map(u64::from).collect());
+            let game = Game::new(nums).map(u64::from);
+            assert!(game.take(5000).eq(reference.take(5000)), "{:?}", nums);
+        }
+    }
+
+    #[test]
+    fn game_jumps_and_remembers_first_turns() {
+        let mut game = Game::new(&[0, 3, 6]).with_first_turns();
+        assert_eq!(game.jump_to(4), 0);
+        assert_eq!(game.jump_to(4), 0);
+        assert_eq!(game.jump_to(10), 0);
+        assert_eq!(game.first_turn(0), Some(1));
+        assert_eq!(game.first_turn(3), Some(2));
+        assert_eq!(game.first_turn(1), Some(7));
+        assert_eq!(game.first_turn(4), Some(9));
+        assert_eq!(game.first_turn(5), None);
+        assert_eq!(game.jump_to(2020), 436);
+        assert_eq!(game.first_turn(436), Some(2020));
+    }
+
+    #[test]
+    fn plays_many_games() {
+        let games = parse(include_str!("../data/day_15_test.txt"));
+        assert_eq!(play_all(&games, 2020), vec![436, 1, 10, 27, 78, 438, 1836]);
+        assert_eq!(play_all(&games[..2], 4), vec![0, 0]);
+        assert_eq!(play_all(&[], 10), Vec::<u32>::new());
+    }
+
+    #[test]
+    fn streams_spoken_numbers() {
+        assert_eq!(
+            spoken(&starting("0,3,6"), 10).collect::<Vec<_>>(),
+            vec![0, 3, 6, 0, 3, 3, 1, 0, 4, 0]
+        );
+        assert_eq!(
+            spoken(&starting("5,6,7"), 2).collect::<Vec<_>>(),
+            vec![5, 6]
+        );
+    }
+}
@@ -0,0 +1,151 @@
+use std::collections::HashMap;
+
+/// Numbers below this many are looked up in a flat array rather than a hash
+/// map, which at four bytes each is at most 256 MiB.
+const DENSE_LIMIT: usize = 1 << 26;
+
+/// The turn each number was spoken on, where turns count from 1.
+///
+/// Every number after the starting ones is less than the turn it's spoken
+/// on, so the flat array grows with the game and only big starting numbers,
+/// or games longer than `DENSE_LIMIT`, need the map.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+struct Turns {
+    /// Indexed by number, with 0 for numbers that haven't been spoken.
+    dense: Vec<u32>,
+    sparse: HashMap<u32, u32>,
+}
+
+impl Turns {
+    /// Makes room for every number below `len` in the flat array.
+    fn reserve(&mut self, len: usize) {
+        let len = len.min(DENSE_LIMIT);
+        if len <= self.dense.len() {
+            return;
+        }
+        self.dense.resize(len, 0);
+        let moved = self
+            .sparse
+            .keys()
+            .copied()
+            .filter(|num| (*num as usize) < len)
+            .collect::<Vec<_>>();
+        for num in moved {
+            self.dense[num as usize] = self.sparse.remove(&num).unwrap();
+        }
+    }
+
+    fn get(&self, num: u32) -> Option<u32> {
+        match self.dense.get(num as usize) {
+            Some(0) => None,
+            Some(turn) => Some(*turn),
+            None => self.sparse.get(&num).copied(),
+        }
+    }
+
+    /// Records a number being spoken, returning the turn it was last spoken
+    /// on.
+    fn replace(&mut self, num: u32, turn: u32) -> Option<u32> {
+        match self.dense.get_mut(num as usize) {
+            Some(slot) => Some(std::mem::replace(slot, turn)).filter(|t| *t != 0),
+            None => self.sparse.insert(num, turn),
+        }
+    }
+}
+
+/// Plays the memory game, where after the starting numbers each turn speaks
+/// how many turns apart the last number's last two appearances were, or 0
+/// if it was new.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Game {
+    starting: Vec<u32>,
+    /// How many turns have been played.
+    turn: u32,
+    last: u32,
+    /// When each number was spoken, not counting the last turn.
+    last_seen: Turns,
+    first_seen: Option<Turns>,
+}
+
+impl Game {
+    pub fn new(starting: &[u32]) -> Self {
+        assert!(!starting.is_empty(), "The game needs a starting number.");
+        Self {
+            starting: starting.to_vec(),
+            turn: 0,
+            last: 0,
+            last_seen: Turns::default(),
+            first_seen: None,
+        }
+    }
+
+    /// Also remembers the first turn each number is spoken on, which takes
+    /// as much memory again.
+    pub fn with_first_turns(mut self) -> Self {
+        assert_eq!(self.turn, 0, "The game has already started.");
+        self.first_seen = Some(Turns::default());
+        self
+    }
+
+    /// Plays one turn, returning the number spoken.
+    pub fn step(&mut self) -> u32 {
+        let len = self.turn as usize + 1;
+        if len > self.last_seen.dense.len() {
+            self.reserve(len * 2);
+        }
+
+        let age = match self.turn {
+            0 => 0,
+            turn => self
+                .last_seen
+                .replace(self.last, turn)
+                .map_or(0, |last| turn - last),
+        };
+        let next = self
+            .starting
+            .get(self.turn as usize)
+            .copied()
+            .unwrap_or(age);
+        self.turn += 1;
+        if let Some(first_seen) = &mut self.first_seen {
+            if first_seen.get(next).is_none() {
+                first_seen.replace(next, self.turn);
+            }
+        }
+        self.last = next;
+        next
+    }
+
+    /// Plays until `turn` turns have been played, returning the number
+    /// spoken on that turn.
+    pub fn jump_to(&mut self, turn: u32) -> u32 {
+        assert!(turn > 0 && turn >= self.turn, "Turn {} has passed.", turn);
+        self.reserve(turn as usize);
+        while self.turn < turn {
+            self.step();
+        }
+        self.last
+    }
+
+    /// The first turn `num` was spoken on so far. Only works on games made
+    /// `with_first_turns`.
+    pub fn first_turn(&self, num: u32) -> Option<u32> {
+        let first_seen = self.first_seen.as_ref();
+        first_seen.expect("First turns aren't tracked.").get(num)
+    }
+
+    fn reserve(&mut self, len: usize) {
+        self.last_seen.reserve(len);
+        if let Some(first_seen) = &mut self.first_seen {
+            first_seen.reserve(len);
+        }
+    }
+}
+
+impl Iterator for Game {
+    type Item = u32;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(self.step())
+    }
+}
@@ -1,42 +1,21 @@
-use itertools::iproduct;
-use std::{
-    collections::{HashMap, HashSet},
-    hash::Hash,
-};
+use crate::automaton::{Automaton, Moore, Rule, Sparse};
 
 pub fn star_1(data: String) {
-    let mut active = parse(&data).map(|(x, y)| (x, y, 0)).collect::<HashSet<_>>();
-    for _ in 0..6 {
-        active = step(&active, neighbors);
-    }
-    println!("{}", active.len());
+    let active = parse(&data).map(|(x, y)| [x, y, 0]);
+    let mut cubes = Sparse::new(Moore::<3>, rule(), active);
+    cubes.run(6);
+    println!("{}", cubes.population());
 }
 
 pub fn star_2(data: String) {
-    let mut active = parse(&data)
-        .map(|(x, y)| (x, y, 0, 0))
-        .collect::<HashSet<_>>();
-    for _ in 0..6 {
-        active = step(&active, neighbors4);
-    }
-    println!("{}", active.len());
+    let active = parse(&data).map(|(x, y)| [x, y, 0, 0]);
+    let mut cubes = Sparse::new(Moore::<4>, rule(), active);
+    cubes.run(6);
+    println!("{}", cubes.population());
 }
 
-pub fn step<T, F, I>(active: &HashSet<T>, neighbors: F) -> HashSet<T>
-where
-    T: Copy + Hash + Eq,
-    F: Fn(T) -> I,
-    I: Iterator<Item = T>,
-{
-    let mut counts = HashMap::new();
-    for coord in active.iter().copied().flat_map(neighbors) {
-        *counts.entry(coord).or_insert(0) += 1;
-    }
-    counts
-        .iter()
-        .filter(|(coord, count)| **count == 3 || (active.contains(coord) && **count == 2))
-        .map(|(c, _)| *c)
-        .collect()
+fn rule() -> Rule {
+    Rule::new(vec![3], vec![2, 3])
 }
 
 fn parse<'a>(data: &'a str) -> impl Iterator<Item = (i64, i64)> + 'a {
@@ -54,15 +33,3 @@ fn parse<'a>(data: &'a str) -> impl Iterator<Item = (i64, i64)> + 'a {
             })
         })
 }
-
-fn neighbors((x, y, z): (i64, i64, i64)) -> impl Iterator<Item = (i64, i64, i64)> {
-    iproduct!(-1..=1, -1..=1, -1..=1)
-        .filter(|o| *o != (0, 0, 0))
-        .map(move |(a, b, c)| (x + a, y + b, z + c))
-}
-
-fn neighbors4((x, y, z, w): (i64, i64, i64, i64)) -> impl Iterator<Item = (i64, i64, i64, i64)> {
-    iproduct!(-1..=1, -1..=1, -1..=1, -1..=1)
-        .filter(|o| *o != (0, 0, 0, 0))
-        .map(move |(a, b, c, d)| (x + a, y + b, z + c, w + d))
-}
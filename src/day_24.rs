@@ -1,3 +1,4 @@
+use crate::automaton::{Automaton, Hex, Rule, Sparse};
 use nom::{branch::alt, bytes::complete::tag, combinator::value, multi::many1, IResult};
 use std::collections::HashMap;
 
@@ -17,36 +18,13 @@ pub fn star_2(data: String) {
     for coord in seqs.iter().map(|s| to_coord(s)) {
         *flipped.entry(coord).or_insert(0) += 1;
     }
-    let mut black = flipped
+    let black = flipped
         .iter()
         .filter(|(_, v)| **v % 2 == 1)
-        .map(|(k, _)| *k)
-        .collect::<Vec<_>>();
-    for _ in 0..100 {
-        let mut counts = HashMap::<Coord, (bool, usize)>::new();
-        let adjs = black.iter().flat_map(|a| {
-            Direction::ALL
-                .iter()
-                .map(|d| d.to_coord_offset())
-                .map(move |b| a + b)
-        });
-        for adj in adjs {
-            let entry = counts.entry(adj).or_insert((false, 0));
-            entry.1 += 1;
-        }
-        for b in black {
-            let entry = counts.entry(b).or_insert((false, 0));
-            entry.0 = true;
-        }
-        black = counts
-            .iter()
-            .filter(|(_, (is_black, count))| {
-                (*is_black && *count > 0 && *count < 3) || (!*is_black && *count == 2)
-            })
-            .map(|(c, _)| *c)
-            .collect();
-    }
-    println!("{}", black.len());
+        .map(|(k, _)| (k.x, k.y));
+    let mut floor = Sparse::new(Hex, Rule::new(vec![2], vec![1, 2]), black);
+    floor.run(100);
+    println!("{}", floor.population());
 }
 
 fn parse(data: &str) -> Vec<Vec<Direction>> {
@@ -125,15 +103,6 @@ enum Direction {
 }
 
 impl Direction {
-    const ALL: [Self; 6] = [
-        Self::East,
-        Self::Southeast,
-        Self::Southwest,
-        Self::West,
-        Self::Northwest,
-        Self::Northeast,
-    ];
-
     fn to_coord_offset(self) -> Coord {
         let (x, y) = match self {
             Self::East => (1, 0),
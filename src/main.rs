@@ -1,5 +1,6 @@
 use std::{env, fs::File, io::Read};
 
+mod automaton;
 mod day_01;
 mod day_02;
 mod day_03;
@@ -25,19 +26,26 @@ mod day_22;
 mod day_23;
 mod day_24;
 mod day_25;
+mod modmath;
 mod utils;
 
 fn main() {
     let mut args = env::args();
     let _ = args.next().unwrap();
     let day = args.next().unwrap().parse::<u32>().unwrap();
-    let star = args.next().unwrap().parse::<u32>().unwrap();
+    let star = args.next().unwrap();
     let filename = args.next().unwrap();
+    let extra = args.collect::<Vec<_>>();
 
     let mut data = String::new();
     let mut file = File::open(filename).unwrap();
     file.read_to_string(&mut data).unwrap();
 
+    let star = match star.parse::<u32>() {
+        Ok(star) => star,
+        Err(_) => return run_tool(day, &star, data, &extra),
+    };
+
     let func = match (day, star) {
         (1, 1) => day_01::star_1,
         (1, 2) => day_01::star_2,
@@ -97,3 +105,35 @@ fn main() {
 
     func(data);
 }
+
+fn run_tool(day: u32, name: &str, data: String, args: &[String]) {
+    let func = match (day, name) {
+        (6, "shared") => day_06::shared,
+        (6, "stats") => day_06::stats,
+        (7, "dot") => day_07::dot,
+        (7, "query") => day_07::query,
+        (7, "validate") => day_07::validate,
+        (8, "analyse") => day_08::analyse,
+        (8, "debug") => day_08::debug,
+        (8, "format") => day_08::format,
+        (8, "run") => day_08::run,
+        (9, "invalid") => day_09::invalid,
+        (9, "weaknesses") => day_09::weaknesses,
+        (10, "chain") => day_10::chain,
+        (11, "animate") => day_11::animate,
+        (12, "path") => day_12::path,
+        (12, "plan") => day_12::plan,
+        (12, "svg") => day_12::svg,
+        (13, "timetable") => day_13::timetable,
+        (14, "trace") => day_14::trace,
+        (15, "bench") => day_15::bench,
+        (15, "first") => day_15::first,
+        (15, "play") => day_15::play,
+        _ => {
+            println!("Invalid day and/or tool.");
+            return;
+        }
+    };
+
+    func(data, args);
+}
@@ -0,0 +1,265 @@
+use num::{integer::ExtendedGcd, Integer, Signed};
+use std::{collections::HashMap, convert::TryFrom};
+
+/// A fixed-width integer that fits in an `i128`.
+///
+/// Everything is worked out in 128 bits, so moduli can use the full 64 bits
+/// without products overflowing.
+pub trait Primitive: Integer + Copy + Into<i128> + TryFrom<i128> {}
+
+impl<T: Integer + Copy + Into<i128> + TryFrom<i128>> Primitive for T {}
+
+/// Converts a modulus, which has to be positive and fit in 64 bits.
+fn modulus<T: Primitive>(m: T) -> u128 {
+    let m = m.into();
+    assert!(
+        m > 0 && m <= i128::from(u64::MAX),
+        "Moduli must be positive and fit in 64 bits."
+    );
+    m as u128
+}
+
+fn residue<T: Primitive>(x: T, m: u128) -> u128 {
+    x.into().rem_euclid(m as i128) as u128
+}
+
+/// Converts back a value that's already known to fit, being less than a
+/// modulus that came from a `T`.
+fn narrow<T: Primitive>(x: u128) -> T {
+    T::try_from(x as i128).ok().unwrap()
+}
+
+pub fn mul_mod<T: Primitive>(a: T, b: T, m: T) -> T {
+    let m = modulus(m);
+    narrow(residue(a, m) * residue(b, m) % m)
+}
+
+/// Raises `base` to a non-negative power by repeated squaring.
+pub fn mod_pow<T: Primitive>(base: T, exp: T, m: T) -> T {
+    let m = modulus(m);
+    let exp = exp.into();
+    assert!(exp >= 0, "Exponents can't be negative.");
+    narrow(pow(residue(base, m), exp as u128, m))
+}
+
+fn pow(mut base: u128, mut exp: u128, m: u128) -> u128 {
+    let mut result = 1 % m;
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = result * base % m;
+        }
+        base = base * base % m;
+        exp >>= 1;
+    }
+    result
+}
+
+/// The `x` in `0..m` with `a * x ≡ 1 (mod m)`, if `a` and `m` are coprime.
+pub fn mod_inv<T: Primitive>(a: T, m: T) -> Option<T> {
+    let m = modulus(m);
+    inv(residue(a, m), m).map(narrow)
+}
+
+fn inv(a: u128, m: u128) -> Option<u128> {
+    // Keeps `r ≡ a * t (mod m)` for both pairs; none of these get bigger in
+    // magnitude than `m`.
+    let (mut r0, mut r1) = (m as i128, a as i128);
+    let (mut t0, mut t1) = (0, 1);
+    while r1 != 0 {
+        let q = r0 / r1;
+        (r0, r1) = (r1, r0 - q * r1);
+        (t0, t1) = (t1, t0 - q * t1);
+    }
+    if r0 == 1 {
+        Some(t0.rem_euclid(m as i128) as u128)
+    } else {
+        None
+    }
+}
+
+/// The smallest `x >= 0` with `base^x ≡ target (mod m)`, found with baby-step
+/// giant-step in O(√m) time and space. `base` must be coprime to `m`.
+pub fn discrete_log<T: Primitive>(base: T, target: T, m: T) -> Option<T> {
+    let wide = modulus(m);
+    let (base, target) = (residue(base, wide) as u64, residue(target, wide) as u64);
+    let m = wide as u64;
+    let steps = (1..).find(|n: &u64| u128::from(*n).pow(2) >= wide).unwrap();
+
+    // Baby steps: base^j for each j below `steps`, keeping the smallest j.
+    let mut baby = HashMap::new();
+    let mut power = 1 % m;
+    for j in 0..steps {
+        baby.entry(power).or_insert(j);
+        power = mul_mod(power, base, m);
+    }
+
+    // Giant steps: target * base^(-steps * i) for increasing i.
+    let giant = mod_inv(mod_pow(base, steps, m), m)?;
+    let mut value = target;
+    for i in 0..steps {
+        if let Some(j) = baby.get(&value) {
+            return Some(narrow(u128::from(i) * u128::from(steps) + u128::from(*j)));
+        }
+        value = mul_mod(value, giant, m);
+    }
+    None
+}
+
+/// Solves a system of congruences `x ≡ r (mod m)`, returning the smallest
+/// non-negative solution along with the modulus every solution is congruent
+/// under, or `None` if there is no solution or a modulus isn't positive.
+///
+/// The moduli don't need to be coprime: the congruences are merged pairwise,
+/// and two congruences can only hold together if their remainders agree
+/// modulo the gcd of their moduli. Intermediate values get as big as the
+/// combined modulus times the largest modulus, so use `BigInt` when that
+/// might not fit.
+pub fn chinese_remainder<T, I>(congruences: I) -> Option<(T, T)>
+where
+    T: Integer + Signed + Clone,
+    I: IntoIterator<Item = (T, T)>,
+{
+    let mut merged = (T::zero(), T::one());
+    for (r, m) in congruences {
+        if !m.is_positive() {
+            return None;
+        }
+        let (r1, m1) = merged;
+        let ExtendedGcd { gcd, x, .. } = m1.extended_gcd(&m);
+        let (diff, rem) = (r - r1.clone()).div_rem(&gcd);
+        if !rem.is_zero() {
+            return None;
+        }
+        // m1 * x ≡ gcd (mod m), so stepping by m1 * x * diff moves from r1
+        // to r without leaving r1's residue class modulo m1.
+        let step = m.clone() / gcd.clone();
+        let lcm = m1.clone() * step.clone();
+        let r = r1 + m1 * (x * diff).mod_floor(&step);
+        merged = (r.mod_floor(&lcm), lcm);
+    }
+    Some(merged)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{chinese_remainder, discrete_log, mod_inv, mod_pow, mul_mod};
+    use num::integer;
+
+    /// A small linear congruential generator, so the property tests are the
+    /// same every run.
+    fn numbers(seed: u64) -> impl Iterator<Item = u64> {
+        let mut state = seed;
+        std::iter::repeat_with(move || {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            state >> 33
+        })
+    }
+
+    #[test]
+    fn mod_pow_matches_repeated_multiplication() {
+        for m in 1..40u32 {
+            for base in 0..m {
+                let mut expected = 1 % m;
+                for exp in 0..30 {
+                    assert_eq!(
+                        mod_pow(base, exp, m),
+                        expected,
+                        "{}^{} mod {}",
+                        base,
+                        exp,
+                        m
+                    );
+                    expected = expected * base % m;
+                }
+            }
+        }
+        assert_eq!(mod_pow(-2i64, 3, 5), 2);
+    }
+
+    #[test]
+    fn mod_pow_does_not_overflow() {
+        // Fermat's little theorem for the Mersenne prime 2^61 - 1.
+        let p = (1u64 << 61) - 1;
+        for a in numbers(1).take(50) {
+            assert_eq!(mod_pow(a % p, p - 1, p), 1);
+        }
+        let m = u64::MAX;
+        assert_eq!(mul_mod(m - 1, m - 1, m), 1);
+        assert_eq!(mod_pow(m - 1, 3, m), m - 1);
+    }
+
+    #[test]
+    fn mod_inv_inverts_coprime_values() {
+        for m in 1..60i64 {
+            for a in -m..m {
+                match mod_inv(a, m) {
+                    Some(x) => {
+                        assert!((0..m).contains(&x));
+                        assert_eq!(mul_mod(a, x, m), 1 % m);
+                    }
+                    None => assert_ne!(integer::gcd(a, m), 1),
+                }
+            }
+        }
+        let p = (1u64 << 61) - 1;
+        for a in numbers(2).take(50).filter(|a| a % p != 0) {
+            let x = mod_inv(a, p).unwrap();
+            assert_eq!(mul_mod(a, x, p), 1);
+        }
+    }
+
+    #[test]
+    fn discrete_log_finds_smallest_exponent() {
+        for &p in &[2u32, 3, 7, 11, 101, 997] {
+            for base in 1..p.min(30) {
+                let mut first = vec![None; p as usize];
+                let mut power = 1;
+                for exp in 0..p {
+                    first[power as usize].get_or_insert(exp);
+                    power = power * base % p;
+                }
+                for target in 0..p {
+                    assert_eq!(discrete_log(base, target, p), first[target as usize]);
+                }
+            }
+        }
+        assert_eq!(discrete_log(7u64, 17807724, 20201227), Some(11));
+        assert_eq!(discrete_log(7u64, 5764801, 20201227), Some(8));
+    }
+
+    #[test]
+    fn chinese_remainder_solves_random_systems() {
+        let mut nums = numbers(3);
+        for _ in 0..500 {
+            let len = nums.next().unwrap() % 4;
+            let x = (nums.next().unwrap() % 10_000) as i64;
+            let congruences = (0..len)
+                .map(|_| {
+                    let m = (nums.next().unwrap() % 30 + 1) as i64;
+                    (x % m, m)
+                })
+                .collect::<Vec<_>>();
+            let lcm = congruences.iter().fold(1, |l, (_, m)| integer::lcm(l, *m));
+            assert_eq!(
+                chinese_remainder(congruences.iter().copied()),
+                Some((x % lcm, lcm))
+            );
+
+            // Nudging one remainder breaks the system unless its modulus
+            // shares nothing with the others.
+            if let Some((r, m)) = congruences.first() {
+                let others = congruences[1..]
+                    .iter()
+                    .fold(1, |l, (_, m)| integer::lcm(l, *m));
+                let mut nudged = congruences.clone();
+                nudged[0] = ((r + 1) % m, *m);
+                let solution = chinese_remainder(nudged.iter().copied());
+                assert_eq!(solution.is_some(), integer::gcd(*m, others) == 1);
+            }
+        }
+        assert_eq!(chinese_remainder(vec![(1, 3), (0, 0)]), None);
+        assert_eq!(chinese_remainder(vec![(1, -3)]), None);
+    }
+}